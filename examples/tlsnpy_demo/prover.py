@@ -37,6 +37,7 @@ class APIProver:
         parsed = urllib.parse.urlparse(url)
         host = parsed.netloc
         port = 443  # Always use HTTPS
+        path = parsed.path or "/"
         
         # Create prover instance
         prover = PyProver(
@@ -53,6 +54,17 @@ class APIProver:
             # Connect to server with retries
             print(f"Connecting to {host}...")
             self._retry_operation(lambda: prover.connect(host, port))
+
+            # Send the request over the notarized connection
+            print(f"Requesting {path}...")
+            request = (
+                f"GET {path} HTTP/1.1\r\n"
+                f"Host: {host}\r\n"
+                "Connection: close\r\n"
+                "\r\n"
+            ).encode()
+            response = prover.send_request(request)
+            print(f"Received {len(response)} bytes")
             
             # Start notarization
             print("Starting notarization...")
@@ -60,12 +72,14 @@ class APIProver:
             
             # Generate proof
             print("Generating proof...")
-            proof = prover.finalize_notarize()
+            proof, secrets = prover.finalize_notarize()
             
-            # Save proof
+            # Save proof and the secrets needed to build presentations from it
             proof_path = self.data_dir / "api_response.proof"
             with open(proof_path, "wb") as f:
                 f.write(proof)
+            with open(self.data_dir / "api_response.secrets", "wb") as f:
+                f.write(secrets)
                 
             print(f"Proof saved to {proof_path}")
             return proof_path
@@ -0,0 +1,122 @@
+"""Checks tlsnpy.pyi against the installed tlsnpy module.
+
+Every class, method and function in the module must be in the stub and vice versa,
+with the same parameter names, kinds and defaulted-ness as the signature pyo3
+generates from `#[pyo3(signature = ...)]`. Types and default values aren't compared,
+as the module doesn't expose them. Run after `maturin develop`:
+
+    python scripts/check_stubs.py [path/to/tlsnpy.pyi]
+"""
+
+import ast
+import inspect
+import sys
+from pathlib import Path
+
+import tlsnpy
+
+STUB = Path(__file__).resolve().parent.parent / "tlsnpy.pyi"
+
+# Parameter names of these aren't part of the interface
+DUNDERS_WITHOUT_SIGNATURES = {"__enter__", "__exit__"}
+
+
+def stub_params(node: ast.FunctionDef):
+    """Returns `(name, kind, has_default)` for each parameter but `self`/`cls`."""
+    args = node.args
+    positional = args.posonlyargs + args.args
+    defaults = [False] * (len(positional) - len(args.defaults)) + [True] * len(args.defaults)
+    params = [
+        (arg.arg, "positional", has_default) for arg, has_default in zip(positional, defaults)
+    ]
+    if args.vararg:
+        params.append((args.vararg.arg, "var_positional", False))
+    for arg, default in zip(args.kwonlyargs, args.kw_defaults):
+        params.append((arg.arg, "keyword_only", default is not None))
+    if args.kwarg:
+        params.append((args.kwarg.arg, "var_keyword", False))
+    is_static = any(
+        isinstance(d, ast.Name) and d.id == "staticmethod" for d in node.decorator_list
+    )
+    return params if is_static else params[1:]
+
+
+def module_params(obj):
+    """Like `stub_params`, from pyo3's text signature of `obj`."""
+    kinds = {
+        inspect.Parameter.POSITIONAL_ONLY: "positional",
+        inspect.Parameter.POSITIONAL_OR_KEYWORD: "positional",
+        inspect.Parameter.VAR_POSITIONAL: "var_positional",
+        inspect.Parameter.KEYWORD_ONLY: "keyword_only",
+        inspect.Parameter.VAR_KEYWORD: "var_keyword",
+    }
+    try:
+        signature = inspect.signature(obj)
+    except (TypeError, ValueError):
+        return None
+    return [
+        (param.name, kinds[param.kind], param.default is not inspect.Parameter.empty)
+        for param in signature.parameters.values()
+        if param.name not in ("self", "cls")
+    ]
+
+
+def compare(name, stub, actual, errors):
+    if actual is not None and stub != actual:
+        errors.append(f"{name}: stub has {stub}, module has {actual}")
+
+
+def check(stub_path: Path):
+    tree = ast.parse(stub_path.read_text())
+    errors = []
+    stub_names = set()
+
+    for node in tree.body:
+        if isinstance(node, ast.FunctionDef):
+            stub_names.add(node.name)
+            obj = getattr(tlsnpy, node.name, None)
+            if obj is None:
+                errors.append(f"{node.name}: in the stub but not in the module")
+                continue
+            compare(node.name, stub_params(node), module_params(obj), errors)
+        elif isinstance(node, ast.ClassDef):
+            stub_names.add(node.name)
+            cls = getattr(tlsnpy, node.name, None)
+            if cls is None:
+                errors.append(f"{node.name}: in the stub but not in the module")
+                continue
+            methods = {m.name: m for m in node.body if isinstance(m, ast.FunctionDef)}
+            if issubclass(cls, BaseException):
+                continue
+            for method_name, method in methods.items():
+                qualified = f"{node.name}.{method_name}"
+                if method_name == "__init__":
+                    compare(qualified, stub_params(method), module_params(cls), errors)
+                    continue
+                if method_name not in vars(cls):
+                    errors.append(f"{qualified}: in the stub but not in the module")
+                elif method_name not in DUNDERS_WITHOUT_SIGNATURES:
+                    obj = getattr(cls, method_name)
+                    compare(qualified, stub_params(method), module_params(obj), errors)
+            for attr in vars(cls):
+                if not attr.startswith("_") and attr not in methods:
+                    errors.append(f"{node.name}.{attr}: in the module but not in the stub")
+
+    for attr in dir(tlsnpy):
+        if not attr.startswith("_") and attr not in stub_names:
+            errors.append(f"{attr}: in the module but not in the stub")
+    return errors
+
+
+def main():
+    stub_path = Path(sys.argv[1]) if len(sys.argv) > 1 else STUB
+    errors = check(stub_path)
+    for error in errors:
+        print(error, file=sys.stderr)
+    if errors:
+        sys.exit(f"{stub_path.name} is out of date: {len(errors)} difference(s)")
+    print(f"{stub_path.name} matches the module")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+#[cfg(feature = "full")]
+use std::io::BufWriter;
+#[cfg(feature = "full")]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bincode::Options;
+#[cfg(feature = "full")]
+use tempfile::NamedTempFile;
+
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyDict;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+#[cfg(feature = "full")]
+use sha2::{Digest, Sha256};
+
+use tlsn_core::attestation::Attestation;
+use tlsn_core::connection::ConnectionInfo;
+#[cfg(feature = "full")]
+use tlsn_core::connection::{ServerCertData, SignatureScheme, TlsVersion};
+use tlsn_core::presentation::Presentation;
+#[cfg(feature = "full")]
+use tlsn_core::Secrets;
+
+use crate::errors::VersionMismatchError;
+use crate::{io_error, sealed};
+
+/// Version of the attestation format produced and read by the tlsn release this crate is
+/// built against.
+pub(crate) const ATTESTATION_VERSION: u64 = 0;
+
+/// Cap in bytes on bincode read or written, set by `set_bincode_limit`; 0 for none.
+static BINCODE_LIMIT: AtomicU64 = AtomicU64::new(0);
+
+/// Serialization format of attestations and secrets handed to Python.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// bincode's default encoding, with fixed-width integers, as `bincode::serialize`.
+    Bincode,
+    /// bincode with variable-width integers, as `bincode::DefaultOptions` and the
+    /// standard configuration of bincode 2 encode them.
+    BincodeVarint,
+    Json,
+}
+
+impl Format {
+    pub(crate) fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "bincode" => Ok(Self::Bincode),
+            "bincode-varint" => Ok(Self::BincodeVarint),
+            "json" => Ok(Self::Json),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown format {name:?}, expected \"bincode\", \"bincode-varint\" or \"json\""
+            ))),
+        }
+    }
+
+    pub(crate) fn encode<T: Serialize>(self, value: &T) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Self::Bincode => fixint_options().serialize(value).map_err(bincode_error)?,
+            Self::BincodeVarint => varint_options().serialize(value).map_err(bincode_error)?,
+            Self::Json => serde_json::to_vec(value)?,
+        })
+    }
+
+    /// Serializes `value` straight into a temporary file in the directory of `path`,
+    /// without building it in memory first.
+    ///
+    /// Persisting the returned file to `path` renames it into place, so a failure never
+    /// leaves a truncated file there.
+    #[cfg(feature = "full")]
+    pub(crate) fn write_temp_file<T: Serialize>(
+        self,
+        path: &str,
+        value: &T,
+    ) -> anyhow::Result<NamedTempFile> {
+        let dir = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty());
+        let file = NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))?;
+        let mut writer = BufWriter::new(file);
+        match self {
+            Self::Bincode => {
+                fixint_options().serialize_into(&mut writer, value).map_err(bincode_error)?
+            }
+            Self::BincodeVarint => {
+                varint_options().serialize_into(&mut writer, value).map_err(bincode_error)?
+            }
+            Self::Json => serde_json::to_writer(&mut writer, value)?,
+        }
+        Ok(writer.into_inner().map_err(|e| e.into_error())?)
+    }
+}
+
+/// Sets a process-wide cap on the size of bincode attestations, secrets and
+/// presentations, or removes it with `None`.
+///
+/// Serializing more than `max_bytes` fails, as does deserializing input that would
+/// decode to more, so untrusted bytes can't make tlsnpy allocate beyond the cap. JSON
+/// isn't affected. There is no cap by default.
+#[pyfunction]
+#[pyo3(signature = (max_bytes = None))]
+pub fn set_bincode_limit(max_bytes: Option<u64>) -> PyResult<()> {
+    if max_bytes == Some(0) {
+        return Err(PyValueError::new_err("max_bytes must be greater than zero"));
+    }
+    BINCODE_LIMIT.store(max_bytes.unwrap_or(0), Ordering::Relaxed);
+    Ok(())
+}
+
+fn bincode_limit() -> u64 {
+    match BINCODE_LIMIT.load(Ordering::Relaxed) {
+        0 => u64::MAX,
+        limit => limit,
+    }
+}
+
+/// The options of `bincode::serialize`/`deserialize`, with the limit applied.
+fn fixint_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(bincode_limit())
+}
+
+fn varint_options() -> impl Options {
+    bincode::DefaultOptions::new().with_limit(bincode_limit())
+}
+
+/// Names the configured cap when bincode hits it.
+fn bincode_error(err: bincode::Error) -> anyhow::Error {
+    match *err {
+        bincode::ErrorKind::SizeLimit => anyhow::anyhow!(
+            "exceeds the bincode limit of {} bytes set by set_bincode_limit()",
+            bincode_limit()
+        ),
+        err => err.into(),
+    }
+}
+
+/// Deserializes bytes in any format.
+///
+/// JSON is tried first when the bytes look like a JSON object, falling back to bincode,
+/// whose encoding may start with the same byte. Bincode is read with fixed-width
+/// integers first and variable-width ones if that fails; the error reported is that of
+/// the default, fixed-width encoding.
+fn decode_any<T: DeserializeOwned>(bytes: &[u8]) -> anyhow::Result<T> {
+    if bytes.trim_ascii_start().starts_with(b"{") {
+        if let Ok(value) = serde_json::from_slice(bytes) {
+            return Ok(value);
+        }
+    }
+    let fixint_err = match fixint_options().deserialize(bytes) {
+        Ok(value) => return Ok(value),
+        Err(e) => bincode_error(e),
+    };
+    varint_options().deserialize(bytes).map_err(|_| fixint_err)
+}
+
+/// Deserializes an attestation encoded as bincode or JSON.
+///
+/// Raises `VersionMismatchError` rather than `ValueError` for a JSON attestation that
+/// fails to decode because its header names another format version.
+pub(crate) fn decode(attestation_bytes: &[u8]) -> PyResult<Attestation> {
+    decode_any(attestation_bytes).map_err(|e| {
+        let version = serde_json::from_slice::<serde_json::Value>(attestation_bytes)
+            .ok()
+            .and_then(|value| value["header"]["version"].as_u64());
+        match version {
+            Some(version) if version != ATTESTATION_VERSION => version_mismatch(version),
+            _ => PyValueError::new_err(format!("Invalid attestation: {e}")),
+        }
+    })
+}
+
+/// Returns the format version in the attestation's header, failing with
+/// `VersionMismatchError` if this build doesn't read that version.
+pub(crate) fn check_version(attestation: &Attestation) -> PyResult<u64> {
+    let header = serde_json::to_value(&attestation.header)
+        .map_err(|e| PyValueError::new_err(format!("Failed to inspect attestation: {e}")))?;
+    header_version(&header)
+}
+
+/// Like `check_version`, for the attestation a presentation was built from, whose
+/// header tlsn only exposes through serialization.
+pub(crate) fn check_presentation_version(presentation: &Presentation) -> PyResult<u64> {
+    let presentation = serde_json::to_value(presentation)
+        .map_err(|e| PyValueError::new_err(format!("Failed to inspect presentation: {e}")))?;
+    header_version(&presentation["attestation"]["header"])
+}
+
+fn header_version(header: &serde_json::Value) -> PyResult<u64> {
+    let version = header["version"]
+        .as_u64()
+        .ok_or_else(|| PyValueError::new_err("Attestation header has no version"))?;
+    if version != ATTESTATION_VERSION {
+        return Err(version_mismatch(version));
+    }
+    Ok(version)
+}
+
+fn version_mismatch(version: u64) -> PyErr {
+    VersionMismatchError::new_err(format!(
+        "Attestation has format version {version}, but this build of tlsnpy reads version \
+         {ATTESTATION_VERSION}; verify it with the tlsn release that produced it"
+    ))
+}
+
+/// Deserializes secrets encoded as bincode or JSON, decrypting them first with
+/// `passphrase` if they were encrypted by `encrypt_secrets`.
+#[cfg(feature = "full")]
+pub(crate) fn decode_secrets(secrets_bytes: &[u8], passphrase: Option<&str>) -> PyResult<Secrets> {
+    let decrypted;
+    let secrets_bytes = match (sealed::is_sealed(secrets_bytes), passphrase) {
+        (true, Some(passphrase)) => {
+            decrypted = open_secrets(secrets_bytes, passphrase)?;
+            &decrypted[..]
+        }
+        (true, None) => {
+            return Err(PyValueError::new_err("Secrets are encrypted; pass their passphrase"));
+        }
+        (false, _) => secrets_bytes,
+    };
+    decode_any(secrets_bytes).map_err(|e| PyValueError::new_err(format!("Invalid secrets: {e}")))
+}
+
+/// Encrypts serialized secrets with `passphrase`, see `encrypt_secrets`.
+pub(crate) fn seal_secrets(secrets_bytes: &[u8], passphrase: &str) -> PyResult<Vec<u8>> {
+    sealed::seal(secrets_bytes, passphrase)
+        .map_err(|e| PyValueError::new_err(format!("Failed to encrypt secrets: {e}")))
+}
+
+fn open_secrets(sealed_bytes: &[u8], passphrase: &str) -> PyResult<Vec<u8>> {
+    sealed::open(sealed_bytes, passphrase)
+        .map_err(|e| PyValueError::new_err(format!("Failed to decrypt secrets: {e}")))
+}
+
+/// Encrypts serialized secrets with a key derived from `passphrase`.
+///
+/// The secrets open every commitment of their attestation, so anyone holding them can
+/// reveal the whole transcript; encrypt them before storing. The key is derived with
+/// Argon2id and the data sealed with XChaCha20-Poly1305. `PyPresentationBuilder`
+/// accepts the result given the same passphrase.
+#[pyfunction]
+pub fn encrypt_secrets(secrets_bytes: Vec<u8>, passphrase: &str) -> PyResult<Vec<u8>> {
+    if sealed::is_sealed(&secrets_bytes) {
+        return Err(PyValueError::new_err("Secrets are already encrypted"));
+    }
+    seal_secrets(&secrets_bytes, passphrase)
+}
+
+/// Decrypts secrets encrypted by `encrypt_secrets`, raising `ValueError` if the
+/// passphrase is wrong or the data was modified.
+#[pyfunction]
+pub fn decrypt_secrets(encrypted_secrets: Vec<u8>, passphrase: &str) -> PyResult<Vec<u8>> {
+    open_secrets(&encrypted_secrets, passphrase)
+}
+
+/// Deserializes a presentation encoded as bincode or JSON.
+pub(crate) fn decode_presentation(presentation_bytes: &[u8]) -> PyResult<Presentation> {
+    decode_any(presentation_bytes).map_err(|e| {
+        let version = serde_json::from_slice::<serde_json::Value>(presentation_bytes)
+            .ok()
+            .and_then(|value| value["attestation"]["header"]["version"].as_u64());
+        match version {
+            Some(version) if version != ATTESTATION_VERSION => version_mismatch(version),
+            _ => PyValueError::new_err(format!("Invalid presentation: {e}")),
+        }
+    })
+}
+
+/// Writes attestation bytes to `path`.
+#[cfg(feature = "full")]
+pub(crate) fn save(attestation_bytes: &[u8], path: &str) -> PyResult<()> {
+    std::fs::write(path, attestation_bytes).map_err(|e| io_error(e, path))
+}
+
+/// Reads a serialized attestation (bincode or JSON) from `path`.
+///
+/// Raises `FileNotFoundError`/`PermissionError` for IO problems and `ValueError` if the
+/// file does not contain an attestation.
+#[pyfunction]
+pub fn load_attestation(path: &str) -> PyResult<Vec<u8>> {
+    let attestation_bytes = std::fs::read(path).map_err(|e| io_error(e, path))?;
+    decode(&attestation_bytes)?;
+    Ok(attestation_bytes)
+}
+
+/// Renders a serialized attestation as pretty-printed JSON for inspection.
+#[pyfunction]
+pub fn attestation_to_json(attestation_bytes: Vec<u8>) -> PyResult<String> {
+    let attestation = decode(&attestation_bytes)?;
+    serde_json::to_string_pretty(&attestation)
+        .map_err(|e| PyValueError::new_err(format!("Failed to encode attestation as JSON: {e}")))
+}
+
+/// Summarizes a serialized attestation for debugging, without verifying it.
+///
+/// Returns a dict with `signature_algorithm`, `time` (Unix seconds of the TLS
+/// connection), `sent_len` and `recv_len`. Nothing is checked against a notary key, so
+/// the values must not be trusted; use `PyVerifier` for that. The server name is not
+/// included because an attestation only holds a commitment to it.
+#[pyfunction]
+pub fn inspect_attestation<'py>(
+    py: Python<'py>,
+    attestation_bytes: Vec<u8>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let attestation = decode(&attestation_bytes)?;
+    let connection = connection_info(&attestation)
+        .map_err(|e| PyValueError::new_err(format!("Failed to inspect attestation: {e}")))?;
+
+    let info = PyDict::new(py);
+    info.set_item("signature_algorithm", attestation.signature.alg.to_string())?;
+    info.set_item("time", connection.time)?;
+    info.set_item("sent_len", connection.transcript_length.sent)?;
+    info.set_item("recv_len", connection.transcript_length.received)?;
+    Ok(info)
+}
+
+/// Returns the Unix time (seconds) of the TLS connection an attestation covers.
+///
+/// The notary sets this when it notarizes the connection, so it shows when the server
+/// said what the transcript holds. Like `inspect_attestation`, nothing is verified.
+#[pyfunction]
+pub fn attestation_time(attestation_bytes: Vec<u8>) -> PyResult<u64> {
+    let attestation = decode(&attestation_bytes)?;
+    let connection = connection_info(&attestation)
+        .map_err(|e| PyValueError::new_err(format!("Failed to inspect attestation: {e}")))?;
+    Ok(connection.time)
+}
+
+/// Returns the size in bytes of an attestation (bincode or JSON) serialized in `format`
+/// (`"bincode"`, the default, `"bincode-varint"` or `"json"`), e.g. to check it against
+/// a storage budget before converting it. Nothing is verified.
+#[pyfunction]
+#[pyo3(signature = (attestation_bytes, format = "bincode"))]
+pub fn attestation_size(attestation_bytes: Vec<u8>, format: &str) -> PyResult<usize> {
+    let format = Format::parse(format)?;
+    let attestation = decode(&attestation_bytes)?;
+    let encoded = format
+        .encode(&attestation)
+        .map_err(|e| PyValueError::new_err(format!("Failed to serialize attestation: {e}")))?;
+    Ok(encoded.len())
+}
+
+/// Compares two serialized attestations field by field, for debugging mismatches.
+///
+/// Returns a dict mapping the dotted path of every differing field (e.g.
+/// `"body.connection_info.time"`) to its `(a, b)` values rendered as JSON, with `None`
+/// for a field only one side has. Byte strings such as hashes and the signature are
+/// compared as a whole. Identical attestations give an empty dict. Nothing is verified.
+#[pyfunction]
+pub fn diff_attestations(a: Vec<u8>, b: Vec<u8>) -> PyResult<HashMap<String, DiffEntry>> {
+    let to_json = |bytes: &[u8]| {
+        serde_json::to_value(decode(bytes)?)
+            .map_err(|e| PyValueError::new_err(format!("Failed to inspect attestation: {e}")))
+    };
+    let mut differences = HashMap::new();
+    diff_values("", Some(&to_json(&a)?), Some(&to_json(&b)?), &mut differences);
+    Ok(differences)
+}
+
+/// A field's value in each attestation, as JSON, or `None` where it is missing.
+type DiffEntry = (Option<String>, Option<String>);
+
+fn diff_values(
+    path: &str,
+    a: Option<&serde_json::Value>,
+    b: Option<&serde_json::Value>,
+    differences: &mut HashMap<String, DiffEntry>,
+) {
+    use serde_json::Value;
+
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{path}.{key}") };
+    match (a, b) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            for key in a.keys().chain(b.keys().filter(|key| !a.contains_key(*key))) {
+                diff_values(&join(key), a.get(key), b.get(key), differences);
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b)))
+            if !is_byte_string(a) || !is_byte_string(b) =>
+        {
+            for index in 0..a.len().max(b.len()) {
+                diff_values(&join(&index.to_string()), a.get(index), b.get(index), differences);
+            }
+        }
+        _ if a == b => {}
+        _ => {
+            let render = |value: Option<&Value>| value.map(Value::to_string);
+            differences.insert(path.to_string(), (render(a), render(b)));
+        }
+    }
+}
+
+/// Whether a JSON array is a serialized byte string, which is compared as one value.
+fn is_byte_string(items: &[serde_json::Value]) -> bool {
+    !items.is_empty() && items.iter().all(serde_json::Value::is_u64)
+}
+
+/// Extracts the DER certificate chain the server presented from serialized secrets,
+/// leaf first.
+///
+/// tlsn keeps the chain inside the server identity opening, see [`server_cert_data`].
+#[cfg(feature = "full")]
+pub(crate) fn server_cert_chain(secrets: &Secrets) -> anyhow::Result<Vec<Vec<u8>>> {
+    let data = server_cert_data(secrets)?;
+    Ok(data.certs.into_iter().map(|cert| cert.0).collect())
+}
+
+/// Returns the SHA-256 fingerprint of the server's leaf certificate in the secrets.
+#[cfg(feature = "full")]
+pub(crate) fn leaf_fingerprint(secrets: &Secrets) -> anyhow::Result<[u8; 32]> {
+    let chain = server_cert_chain(secrets)?;
+    let leaf = chain
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("secrets contain no server certificates"))?;
+    Ok(Sha256::digest(leaf).into())
+}
+
+/// Protocol parameters of a notarized TLS connection.
+#[cfg(feature = "full")]
+#[derive(Debug, Clone)]
+pub(crate) struct TlsInfo {
+    /// `"TLSv1.2"` or `"TLSv1.3"`.
+    pub(crate) version: String,
+    /// IANA name of the cipher suite.
+    pub(crate) cipher_suite: String,
+}
+
+/// Reads the TLS version from the attestation and the cipher suite from the server's
+/// handshake signature in the secrets.
+///
+/// MPC-TLS only negotiates ECDHE key exchange with AES-128-GCM and SHA-256, so the
+/// suite is determined by whether the server signed the key exchange with ECDSA or RSA.
+#[cfg(feature = "full")]
+pub(crate) fn tls_info(attestation: &Attestation, secrets: &Secrets) -> anyhow::Result<TlsInfo> {
+    let version = match connection_info(attestation)?.version {
+        TlsVersion::V1_2 => "TLSv1.2",
+        TlsVersion::V1_3 => "TLSv1.3",
+    };
+
+    let ecdsa = matches!(
+        server_cert_data(secrets)?.sig.scheme,
+        SignatureScheme::ECDSA_SHA1_Legacy
+            | SignatureScheme::ECDSA_NISTP256_SHA256
+            | SignatureScheme::ECDSA_NISTP384_SHA384
+            | SignatureScheme::ECDSA_NISTP521_SHA512
+    );
+    let cipher_suite = if ecdsa {
+        "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256"
+    } else {
+        "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"
+    };
+
+    Ok(TlsInfo { version: version.to_string(), cipher_suite: cipher_suite.to_string() })
+}
+
+/// Reads the connection info from the body of an attestation.
+///
+/// tlsn has no public accessor for it, so it is read back from the body's serialized
+/// form, where each field is an `{"id": ..., "data": ...}` object.
+fn connection_info(attestation: &Attestation) -> anyhow::Result<ConnectionInfo> {
+    let body = serde_json::to_value(&attestation.body)?;
+    let data = body
+        .pointer("/connection_info/data")
+        .ok_or_else(|| anyhow::anyhow!("attestation contains no connection info"))?;
+    Ok(serde_json::from_value(data.clone())?)
+}
+
+/// Reads the server's certificates and handshake signature from the identity opening
+/// in the secrets.
+///
+/// Like the connection info, the opening has no public accessor and is read back from
+/// its serialized form.
+#[cfg(feature = "full")]
+fn server_cert_data(secrets: &Secrets) -> anyhow::Result<ServerCertData> {
+    let proof = serde_json::to_value(secrets.identity_proof())?;
+    let data = proof
+        .pointer("/opening/data")
+        .ok_or_else(|| anyhow::anyhow!("secrets contain no server certificate data"))?;
+    Ok(serde_json::from_value(data.clone())?)
+}
@@ -0,0 +1,83 @@
+//! Cancellation of in-flight prover operations.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use pyo3::exceptions::PyInterruptedError;
+use pyo3::prelude::*;
+
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+/// How often a blocking call checks for signals such as Ctrl-C.
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Hands out tokens to operations as they start and cancels them on request.
+///
+/// Each cancellation replaces the token, so operations started afterwards run normally.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Canceller(Arc<Mutex<CancellationToken>>);
+
+impl Canceller {
+    /// Returns the token for an operation that is about to start.
+    pub(crate) fn token(&self) -> CancellationToken {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Cancels every operation currently in flight.
+    pub(crate) fn cancel(&self) {
+        let mut token = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        token.cancel();
+        *token = CancellationToken::new();
+    }
+}
+
+/// Cancels the operations of the prover it was taken from.
+///
+/// Unlike the prover itself, a handle may be passed to other threads, so a blocking
+/// call on the prover's thread can be cancelled from elsewhere.
+#[pyclass]
+pub struct PyCancelHandle {
+    canceller: Canceller,
+}
+
+impl PyCancelHandle {
+    pub(crate) fn new(canceller: Canceller) -> Self {
+        Self { canceller }
+    }
+}
+
+#[pymethods]
+impl PyCancelHandle {
+    /// Cancels the prover's in-flight operation, if any.
+    fn cancel(&self) {
+        self.canceller.cancel();
+    }
+}
+
+/// Runs `fut` until it completes, failing with `InterruptedError` if `token` is
+/// cancelled first. `fut` is dropped on cancellation.
+pub(crate) async fn cancellable<F: Future>(token: CancellationToken, fut: F) -> PyResult<F::Output> {
+    tokio::select! {
+        output = fut => Ok(output),
+        _ = token.cancelled() => Err(PyInterruptedError::new_err("Operation was cancelled")),
+    }
+}
+
+/// Runs `fut` until it completes, running Python's signal handlers meanwhile. If one
+/// raises, e.g. `KeyboardInterrupt` for Ctrl-C, `fut` is dropped and the exception
+/// returned.
+///
+/// Python only handles signals on the main thread; elsewhere this just runs `fut`.
+pub(crate) async fn interruptible<F: Future>(fut: F) -> PyResult<F::Output> {
+    let mut ticks = tokio::time::interval(SIGNAL_CHECK_INTERVAL);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    tokio::pin!(fut);
+    loop {
+        tokio::select! {
+            output = &mut fut => return Ok(output),
+            _ = ticks.tick() => Python::with_gil(|py| py.check_signals())?,
+        }
+    }
+}
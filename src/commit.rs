@@ -0,0 +1,207 @@
+//! What the prover asks the notary to commit to when finalizing a session.
+
+use std::ops::Range;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use tlsn_core::hash::HashAlgId;
+use tlsn_core::request::RequestConfig;
+use tlsn_core::signing::SignatureAlgId;
+use tlsn_core::transcript::{
+    Direction, Transcript, TranscriptCommitConfig, TranscriptCommitmentKind,
+};
+
+/// Upper bound on the commitments per direction a granularity may produce, as each
+/// adds to the size of the attestation and the work of the notary.
+const MAX_COMMITMENTS_PER_DIRECTION: usize = 1024;
+
+/// Hash algorithm, commitment kinds and granularity for `finalize_notarize`.
+#[derive(Debug, Clone)]
+pub(crate) struct CommitOptions {
+    hash_alg: HashAlgId,
+    kinds: Vec<TranscriptCommitmentKind>,
+    /// Size of the chunks each direction is committed in; `None` commits it whole.
+    sent_granularity: Option<usize>,
+    recv_granularity: Option<usize>,
+    /// The only ranges of each direction to commit, sorted; `None` commits all of it.
+    sent_ranges: Option<Vec<Range<usize>>>,
+    recv_ranges: Option<Vec<Range<usize>>>,
+}
+
+impl CommitOptions {
+    /// Parses the `hash_alg`, `commitments`, `(sent_granularity, recv_granularity)` and
+    /// `(sent_commit_ranges, recv_commit_ranges)` arguments of `finalize_notarize`.
+    ///
+    /// `commitments` lists `"encoding"` and/or `"hash"`, defaulting to `["encoding"]`.
+    /// The notary can only prove plaintext hashes with SHA-256, so `"hash"` requires it.
+    /// Commit ranges are `(start, end)` pairs that must not overlap; a direction can't
+    /// have both ranges and a granularity.
+    pub(crate) fn parse(
+        hash_alg: &str,
+        commitments: Option<Vec<String>>,
+        (sent_granularity, recv_granularity): (Option<usize>, Option<usize>),
+        (sent_ranges, recv_ranges): (Option<Vec<(usize, usize)>>, Option<Vec<(usize, usize)>>),
+    ) -> PyResult<Self> {
+        for (name, granularity) in
+            [("sent_granularity", sent_granularity), ("recv_granularity", recv_granularity)]
+        {
+            if granularity == Some(0) {
+                return Err(PyValueError::new_err(format!("{name} must be greater than zero")));
+            }
+        }
+        if sent_ranges.is_some() && sent_granularity.is_some() {
+            return Err(PyValueError::new_err(
+                "sent_commit_ranges and sent_granularity can't be combined",
+            ));
+        }
+        if recv_ranges.is_some() && recv_granularity.is_some() {
+            return Err(PyValueError::new_err(
+                "recv_commit_ranges and recv_granularity can't be combined",
+            ));
+        }
+        let sent_ranges = sent_ranges.map(|r| commit_ranges("sent_commit_ranges", r)).transpose()?;
+        let recv_ranges = recv_ranges.map(|r| commit_ranges("recv_commit_ranges", r)).transpose()?;
+        if sent_ranges.as_ref().is_some_and(Vec::is_empty)
+            && recv_ranges.as_ref().is_some_and(Vec::is_empty)
+        {
+            return Err(PyValueError::new_err(
+                "sent_commit_ranges and recv_commit_ranges are both empty, leaving nothing to \
+                 commit",
+            ));
+        }
+
+        let hash_alg = match hash_alg {
+            "sha256" => HashAlgId::SHA256,
+            "blake3" => HashAlgId::BLAKE3,
+            "keccak256" => HashAlgId::KECCAK256,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown hash algorithm {hash_alg:?}, expected \"sha256\", \"blake3\" or \
+                     \"keccak256\""
+                )));
+            }
+        };
+
+        let commitments = commitments.unwrap_or_else(|| vec!["encoding".to_string()]);
+        if commitments.is_empty() {
+            return Err(PyValueError::new_err("commitments must not be empty"));
+        }
+        let mut kinds = Vec::new();
+        for commitment in &commitments {
+            let kind = match commitment.as_str() {
+                "encoding" => TranscriptCommitmentKind::Encoding,
+                "hash" if hash_alg != HashAlgId::SHA256 => {
+                    return Err(PyValueError::new_err(
+                        "\"hash\" commitments are only supported with hash_alg=\"sha256\"",
+                    ));
+                }
+                "hash" => TranscriptCommitmentKind::Hash { alg: hash_alg },
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unknown commitment {commitment:?}, expected \"encoding\" or \"hash\""
+                    )));
+                }
+            };
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+
+        Ok(Self { hash_alg, kinds, sent_granularity, recv_granularity, sent_ranges, recv_ranges })
+    }
+
+    /// Builds the request committing to the whole transcript with each requested kind,
+    /// so any part of it can be revealed later.
+    ///
+    /// A direction with a granularity is committed as consecutive chunks of that many
+    /// bytes, the last one holding the remainder. Fails if that takes more than
+    /// `MAX_COMMITMENTS_PER_DIRECTION` commitments. A direction with commit ranges is
+    /// committed in just those ranges, which must lie within the transcript.
+    pub(crate) fn request_config(
+        &self,
+        transcript: &Transcript,
+        signature_alg: SignatureAlgId,
+    ) -> anyhow::Result<RequestConfig> {
+        let mut commit_builder = TranscriptCommitConfig::builder(transcript);
+        commit_builder.encoding_hash_alg(self.hash_alg);
+        for (direction, len, granularity, ranges) in [
+            (Direction::Sent, transcript.sent().len(), self.sent_granularity, &self.sent_ranges),
+            (
+                Direction::Received,
+                transcript.received().len(),
+                self.recv_granularity,
+                &self.recv_ranges,
+            ),
+        ] {
+            if let Some(ranges) = ranges {
+                for range in ranges {
+                    if range.end > len {
+                        anyhow::bail!(
+                            "commit range ({}, {}) is outside the {len} bytes {direction:?}",
+                            range.start,
+                            range.end
+                        );
+                    }
+                    for kind in &self.kinds {
+                        commit_builder.commit_with_kind(range, direction, *kind)?;
+                    }
+                }
+                continue;
+            }
+            if len == 0 {
+                continue;
+            }
+            let chunk = granularity.unwrap_or(len);
+            let chunks = len.div_ceil(chunk);
+            if chunks > MAX_COMMITMENTS_PER_DIRECTION {
+                anyhow::bail!(
+                    "a granularity of {chunk} bytes splits the {len} bytes {direction:?} into \
+                     {chunks} commitments, more than the {MAX_COMMITMENTS_PER_DIRECTION} allowed"
+                );
+            }
+            for start in (0..len).step_by(chunk) {
+                let range = start..len.min(start + chunk);
+                for kind in &self.kinds {
+                    commit_builder.commit_with_kind(&range, direction, *kind)?;
+                }
+            }
+        }
+
+        let mut request_builder = RequestConfig::builder();
+        request_builder
+            .signature_alg(signature_alg)
+            .hash_alg(self.hash_alg)
+            .transcript_commit(commit_builder.build()?);
+        Ok(request_builder.build()?)
+    }
+}
+
+/// Validates the `(start, end)` commit ranges of one direction, returning them sorted.
+fn commit_ranges(name: &str, ranges: Vec<(usize, usize)>) -> PyResult<Vec<Range<usize>>> {
+    if ranges.len() > MAX_COMMITMENTS_PER_DIRECTION {
+        return Err(PyValueError::new_err(format!(
+            "{name} has {} ranges, more than the {MAX_COMMITMENTS_PER_DIRECTION} allowed",
+            ranges.len()
+        )));
+    }
+    let mut ranges: Vec<Range<usize>> = ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start >= end {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid range ({start}, {end}) in {name}"
+                )));
+            }
+            Ok(start..end)
+        })
+        .collect::<PyResult<_>>()?;
+    ranges.sort_by_key(|range| range.start);
+    if let Some(pair) = ranges.windows(2).find(|pair| pair[1].start < pair[0].end) {
+        return Err(PyValueError::new_err(format!(
+            "Ranges ({}, {}) and ({}, {}) in {name} overlap",
+            pair[0].start, pair[0].end, pair[1].start, pair[1].end
+        )));
+    }
+    Ok(ranges)
+}
@@ -0,0 +1,258 @@
+//! Python exception types raised by the bindings.
+//!
+//! Every error derives from `TlsnError`, which itself derives from `RuntimeError`
+//! so existing `except RuntimeError` blocks keep working.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+create_exception!(tlsnpy, TlsnError, PyRuntimeError, "Base class for tlsnpy errors.");
+create_exception!(
+    tlsnpy,
+    NotaryConnectionError,
+    TlsnError,
+    "The notary or the server could not be reached, or the connection was lost."
+);
+create_exception!(
+    tlsnpy,
+    AuthorizationError,
+    TlsnError,
+    "The notary rejected the request's credentials."
+);
+create_exception!(
+    tlsnpy,
+    ProtocolLimitError,
+    TlsnError,
+    "The session would exceed the negotiated data limits."
+);
+create_exception!(
+    tlsnpy,
+    NotaryBusyError,
+    TlsnError,
+    "The notary is at capacity and turned the session away; try again later."
+);
+create_exception!(
+    tlsnpy,
+    VersionMismatchError,
+    TlsnError,
+    "The attestation was produced by a tlsn release this build is incompatible with."
+);
+create_exception!(
+    tlsnpy,
+    TimeoutError,
+    TlsnError,
+    "An operation did not complete within the configured timeout."
+);
+
+/// Registers the exception types on the module.
+pub(crate) fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("TlsnError", py.get_type::<TlsnError>())?;
+    m.add("NotaryConnectionError", py.get_type::<NotaryConnectionError>())?;
+    m.add("AuthorizationError", py.get_type::<AuthorizationError>())?;
+    m.add("ProtocolLimitError", py.get_type::<ProtocolLimitError>())?;
+    m.add("NotaryBusyError", py.get_type::<NotaryBusyError>())?;
+    m.add("VersionMismatchError", py.get_type::<VersionMismatchError>())?;
+    m.add("TimeoutError", py.get_type::<TimeoutError>())?;
+    Ok(())
+}
+
+/// The notary turning down a session or notarization request, with the reason it gave.
+#[cfg(feature = "full")]
+#[derive(Debug)]
+pub(crate) struct Rejection {
+    pub(crate) status: u16,
+    pub(crate) reason: String,
+}
+
+#[cfg(feature = "full")]
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.reason.is_empty() {
+            write!(f, "HTTP {}, no reason given", self.status)
+        } else {
+            write!(f, "HTTP {}: {}", self.status, self.reason)
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+impl std::error::Error for Rejection {}
+
+/// Why the notary turned a request away.
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RejectionKind {
+    Unauthorized,
+    Limit,
+    Busy,
+}
+
+#[cfg(feature = "full")]
+impl Rejection {
+    /// Reads the reason out of an error response body: the `message` of a JSON body,
+    /// as the gateway sends, or the body itself, as the notary server sends.
+    pub(crate) fn from_response(status: u16, body: &[u8]) -> Self {
+        let message = serde_json::from_slice::<serde_json::Value>(body)
+            .ok()
+            .and_then(|json| json["message"].as_str().map(str::to_string));
+        let reason = message.unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_string());
+        Self { status, reason }
+    }
+
+    /// Classifies by status, falling back to the reason for statuses such as 400 that
+    /// the notary server answers data limits over its own with.
+    fn kind(&self) -> Option<RejectionKind> {
+        match self.status {
+            401 | 403 => Some(RejectionKind::Unauthorized),
+            413 => Some(RejectionKind::Limit),
+            429 | 503 => Some(RejectionKind::Busy),
+            _ => {
+                let reason = self.reason.to_ascii_lowercase();
+                (reason.contains("exceed") || reason.contains("limit"))
+                    .then_some(RejectionKind::Limit)
+            }
+        }
+    }
+}
+
+/// Converts a failure of `phase` into the most specific exception type, keeping the
+/// full error chain in the message.
+///
+/// Failures with a known remedy, such as exceeding the data limits, lead the message
+/// with what to do about them. Rejections by the notary are classified by their status
+/// and reason: limits over the notary's as `ProtocolLimitError`, credentials as
+/// `AuthorizationError` and capacity as `NotaryBusyError`.
+#[cfg(feature = "full")]
+pub(crate) fn classify(phase: &str, err: &anyhow::Error) -> PyErr {
+    let hint = protocol_hint(err);
+    let message = match hint {
+        Some(hint) => format!("{phase} failed: {}; caused by: {err:#}", hint.advice()),
+        None => format!("{phase} failed: {err:#}"),
+    };
+    let rejection = err.chain().find_map(|cause| cause.downcast_ref::<Rejection>());
+    match rejection.and_then(Rejection::kind) {
+        Some(RejectionKind::Limit) => return ProtocolLimitError::new_err(message),
+        Some(RejectionKind::Unauthorized) => return AuthorizationError::new_err(message),
+        Some(RejectionKind::Busy) => return NotaryBusyError::new_err(message),
+        None => {}
+    }
+
+    if matches!(hint, Some(ProtocolHint::SentLimit | ProtocolHint::RecvLimit)) {
+        ProtocolLimitError::new_err(message)
+    } else if matches!(response_status(err), Some(429 | 503)) {
+        NotaryBusyError::new_err(message)
+    } else if matches!(response_status(err), Some(401 | 403)) {
+        AuthorizationError::new_err(message)
+    } else if is_connection_error(err) {
+        NotaryConnectionError::new_err(message)
+    } else {
+        TlsnError::new_err(message)
+    }
+}
+
+/// MPC protocol failures users can act on.
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProtocolHint {
+    SentLimit,
+    RecvLimit,
+    ObliviousTransfer,
+}
+
+#[cfg(feature = "full")]
+impl ProtocolHint {
+    fn advice(self) -> &'static str {
+        match self {
+            Self::SentLimit => {
+                "more data was sent than max_sent_data allows; raise max_sent_data (and \
+                 notary_max_sent_data if set) or send a smaller request"
+            }
+            Self::RecvLimit => {
+                "the server sent more data than max_recv_data allows; raise max_recv_data \
+                 (and notary_max_recv_data if set) or request less, e.g. with a Range header"
+            }
+            Self::ObliviousTransfer => {
+                "the oblivious transfer with the notary failed, usually because the notary \
+                 connection dropped or the notary runs an incompatible tlsn version"
+            }
+        }
+    }
+}
+
+/// Recognizes MPC protocol failures in the rendered chain, since tlsn doesn't expose
+/// its error kinds.
+#[cfg(feature = "full")]
+fn protocol_hint(err: &anyhow::Error) -> Option<ProtocolHint> {
+    err.chain().find_map(|cause| {
+        let cause = cause.to_string().to_ascii_lowercase();
+        let over_limit = ["exceed", "limit", "too much", "more than"]
+            .iter()
+            .any(|phrase| cause.contains(phrase));
+        if over_limit && (cause.contains("max_sent") || cause.contains("sent data")) {
+            Some(ProtocolHint::SentLimit)
+        } else if over_limit
+            && ["max_recv", "recv data", "received data"].iter().any(|name| cause.contains(name))
+        {
+            Some(ProtocolHint::RecvLimit)
+        } else if cause.contains("oblivious transfer") || cause.contains("ot error") {
+            Some(ProtocolHint::ObliviousTransfer)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns whether `err` was caused by a network-level failure worth retrying.
+#[cfg(feature = "full")]
+pub(crate) fn is_connection_error(err: &anyhow::Error) -> bool {
+    use std::io::ErrorKind;
+
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .any(|io| {
+            matches!(
+                io.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::NotConnected
+                    | ErrorKind::TimedOut
+                    | ErrorKind::UnexpectedEof
+            )
+        })
+}
+
+/// Whether the TLS handshake with the server was refused with an alert that servers
+/// also send when they require a client certificate.
+#[cfg(feature = "full")]
+pub(crate) fn is_handshake_rejection(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        let cause = cause.to_string().to_ascii_lowercase();
+        ["handshakefailure", "badcertificate", "certificaterequired"]
+            .iter()
+            .any(|alert| cause.contains(alert))
+    })
+}
+
+/// Returns the status of the notary's error response that `err` reports.
+///
+/// `NotaryClient` doesn't expose the response it failed on, only its `Debug` form in
+/// the message, so the status is read from the `status: 503` field of that rather than
+/// matched anywhere in the chain, where ports, addresses and sizes hold digits too.
+#[cfg(feature = "full")]
+fn response_status(err: &anyhow::Error) -> Option<u16> {
+    err.chain().find_map(|cause| {
+        let cause = cause.to_string();
+        cause.match_indices("status: ").find_map(|(start, field)| {
+            let value = &cause[start + field.len()..];
+            let digits = value.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 3 {
+                value[..digits].parse().ok()
+            } else {
+                None
+            }
+        })
+    })
+}
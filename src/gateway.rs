@@ -0,0 +1,678 @@
+//! A front end that screens notarization requests before the notary server sees them.
+//!
+//! `run_server` offers no hooks into how sessions are admitted, so policies the notary
+//! server lacks are enforced here. The gateway listens on the notary's public address,
+//! inspects each `POST /session` request and forwards accepted ones to a notary server
+//! on a private loopback port.
+//!
+//! Each connection carries one screened request. Requests are forwarded with
+//! `Connection: close` and the connection ends after their response, so a client can't
+//! follow an innocuous request with one the gateway never saw. Only once the notary
+//! server has accepted a notarization upgrade is the connection relayed as is.
+//!
+//! That server can be replaced while the gateway runs, e.g. to rotate the signing key:
+//! new sessions go to the replacement while sessions already issued finish on the
+//! server that issued them, which is retired once nothing uses it any more.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use tokio_rustls::rustls::{self, Certificate, PrivateKey};
+use tokio_rustls::TlsAcceptor;
+
+use crate::whitelist::Whitelist;
+use crate::{http, net};
+
+/// Upper bound on the size of a request body, e.g. that of `POST /session`.
+const MAX_REQUEST_BODY: usize = 64 * 1024;
+/// Upper bound on the size of a response relayed from the notary server.
+const MAX_RESPONSE: usize = 64 * 1024;
+/// Pause after a failed accept (e.g. out of file descriptors) before trying again.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(50);
+/// Upper bound on the issued sessions remembered until their notarization starts.
+const MAX_PENDING_SESSIONS: usize = 1024;
+/// How often `Policy::idle` checks whether the last notarization has ended.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// How often `Routes::retired` checks whether a replaced server is still used.
+const RETIRE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Data limits a client may request for a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Limits {
+    pub(crate) max_sent_data: usize,
+    pub(crate) max_recv_data: usize,
+}
+
+/// What the gateway admits.
+#[derive(Debug)]
+pub(crate) struct Policy {
+    /// Ceilings for clients without a tenant entry.
+    pub(crate) default_limits: Limits,
+    /// Per-tenant ceilings, keyed by API key.
+    pub(crate) tenant_limits: HashMap<String, Limits>,
+    /// API keys allowed to request sessions; `None` admits everyone.
+    pub(crate) whitelist: Option<Whitelist>,
+    /// Most notarizations relayed at once; `None` for no limit.
+    pub(crate) max_sessions: Option<usize>,
+    /// One permit per notarization that may still start, held while it is relayed.
+    pub(crate) sessions: Arc<Semaphore>,
+}
+
+impl Policy {
+    /// Returns the limits the upstream notary server must allow so that every tenant's
+    /// ceiling can be granted.
+    pub(crate) fn upstream_limits(&self) -> Limits {
+        self.tenant_limits.values().fold(self.default_limits, |acc, limits| Limits {
+            max_sent_data: acc.max_sent_data.max(limits.max_sent_data),
+            max_recv_data: acc.max_recv_data.max(limits.max_recv_data),
+        })
+    }
+
+    /// A policy admitting every session within `limits`, for a gateway that only holds
+    /// the notary's public address.
+    pub(crate) fn open(limits: Limits) -> Self {
+        Self {
+            default_limits: limits,
+            tenant_limits: HashMap::new(),
+            whitelist: None,
+            max_sessions: None,
+            sessions: Arc::new(Semaphore::new(Self::session_permits(None))),
+        }
+    }
+
+    /// Returns the permits `sessions` starts with for `max_sessions`.
+    pub(crate) fn session_permits(max_sessions: Option<usize>) -> usize {
+        max_sessions.unwrap_or(Semaphore::MAX_PERMITS)
+    }
+
+    /// Returns how many notarizations are being relayed.
+    pub(crate) fn active_sessions(&self) -> usize {
+        Self::session_permits(self.max_sessions) - self.sessions.available_permits()
+    }
+
+    /// Waits until no notarization is being relayed.
+    pub(crate) async fn idle(&self) {
+        while self.active_sessions() > 0 {
+            tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+        }
+    }
+
+    fn busy(&self) -> Admission {
+        Admission::Reject {
+            status: "503 Service Unavailable",
+            reason: format!(
+                "Notary server busy: {} notarizations in progress, try again later",
+                self.active_sessions()
+            ),
+        }
+    }
+
+    fn limits_for(&self, api_key: Option<&str>) -> Limits {
+        api_key
+            .and_then(|key| self.tenant_limits.get(key))
+            .copied()
+            .unwrap_or(self.default_limits)
+    }
+}
+
+/// Which notary server each connection is forwarded to.
+#[derive(Debug)]
+pub(crate) struct Routes {
+    state: Mutex<RouteState>,
+}
+
+#[derive(Debug)]
+struct RouteState {
+    /// Server new sessions are requested from.
+    current: SocketAddr,
+    /// Servers that issued the most recent sessions, by session id, since a session
+    /// can only be notarized by the server that issued it.
+    sessions: VecDeque<(String, SocketAddr)>,
+    /// How many connections are being relayed to each server.
+    relayed: HashMap<SocketAddr, usize>,
+    /// Servers `switch` replaced that haven't been retired yet.
+    replaced: HashSet<SocketAddr>,
+}
+
+impl Routes {
+    pub(crate) fn new(upstream: SocketAddr) -> Self {
+        Self {
+            state: Mutex::new(RouteState {
+                current: upstream,
+                sessions: VecDeque::new(),
+                relayed: HashMap::new(),
+                replaced: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Sends new sessions to `upstream`, returning the server they went to before.
+    pub(crate) fn switch(&self, upstream: SocketAddr) -> SocketAddr {
+        let mut state = self.lock();
+        let previous = std::mem::replace(&mut state.current, upstream);
+        state.replaced.insert(previous);
+        previous
+    }
+
+    /// Waits until `upstream` has been replaced and no connection can reach it any more:
+    /// none is being relayed to it and it issued no session that is still to be
+    /// notarized.
+    pub(crate) async fn retired(&self, upstream: SocketAddr) {
+        loop {
+            {
+                let mut state = self.lock();
+                let used = state.current == upstream
+                    || state.relayed.contains_key(&upstream)
+                    || state.sessions.iter().any(|(_, issuer)| *issuer == upstream);
+                if !used && state.replaced.remove(&upstream) {
+                    return;
+                }
+            }
+            tokio::time::sleep(RETIRE_POLL_INTERVAL).await;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RouteState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Picks the server for `route`, counting the connection against it until the
+    /// returned relay is dropped. Notarizations go to the server that issued their
+    /// session, which is forgotten, or to the current one if the session is unknown.
+    fn open(&self, route: &Route) -> Relay<'_> {
+        let mut state = self.lock();
+        let issuer = match route {
+            Route::Notarize(Some(session_id)) => state
+                .sessions
+                .iter()
+                .position(|(id, _)| id == session_id)
+                .and_then(|index| state.sessions.remove(index))
+                .map(|(_, issuer)| issuer),
+            _ => None,
+        };
+        let upstream = issuer.unwrap_or(state.current);
+        *state.relayed.entry(upstream).or_default() += 1;
+        Relay { routes: self, upstream }
+    }
+
+    fn record(&self, session_id: String, upstream: SocketAddr) {
+        let mut state = self.lock();
+        if state.sessions.len() == MAX_PENDING_SESSIONS {
+            state.sessions.pop_front();
+        }
+        state.sessions.push_back((session_id, upstream));
+    }
+}
+
+/// A connection being relayed to `upstream`, counted by `routes` until dropped.
+struct Relay<'a> {
+    routes: &'a Routes,
+    upstream: SocketAddr,
+}
+
+impl Drop for Relay<'_> {
+    fn drop(&mut self) {
+        let mut state = self.routes.lock();
+        if let Some(count) = state.relayed.get_mut(&self.upstream) {
+            *count -= 1;
+            if *count == 0 {
+                state.relayed.remove(&self.upstream);
+            }
+        }
+    }
+}
+
+/// The public side the gateway accepts connections on.
+pub(crate) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Accepts a connection, returning it with a description of the peer for logging.
+    async fn accept(&self) -> std::io::Result<(Box<dyn Connection>, String)> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, peer) = listener.accept().await?;
+                Ok((Box::new(stream), peer.to_string()))
+            }
+            #[cfg(unix)]
+            Self::Unix(listener) => {
+                let (stream, _) = listener.accept().await?;
+                Ok((Box::new(stream), "unix socket".to_string()))
+            }
+        }
+    }
+}
+
+trait Connection: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Connection for T {}
+
+/// Builds the TLS acceptor for the public side from the notary's PEM files.
+pub(crate) fn tls_acceptor(cert_path: &str, key_path: &str) -> anyhow::Result<TlsAcceptor> {
+    let mut cert_reader = std::io::BufReader::new(std::fs::File::open(cert_path)?);
+    let certs = rustls_pemfile::certs(&mut cert_reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+
+    let mut key_reader = std::io::BufReader::new(std::fs::File::open(key_path)?);
+    let key = net::private_key_from_pem(&mut key_reader)
+        .map(PrivateKey)
+        .map_err(|e| e.context(format!("reading {key_path}")))?;
+
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Accepts connections on `listener` and forwards admitted ones as `routes` directs.
+///
+/// Runs until an unrecoverable error; dropping the future closes every connection.
+pub(crate) async fn serve(
+    listener: Listener,
+    routes: Arc<Routes>,
+    tls: Option<TlsAcceptor>,
+    policy: Arc<Policy>,
+) -> anyhow::Result<()> {
+    let mut connections = JoinSet::new();
+    loop {
+        while connections.try_join_next().is_some() {}
+
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!("Gateway failed to accept a connection: {e}");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+
+        let tls = tls.clone();
+        let routes = routes.clone();
+        let policy = policy.clone();
+        connections.spawn(async move {
+            let result = match tls {
+                Some(tls) => match tls.accept(stream).await {
+                    Ok(stream) => handle(stream, &routes, &policy).await,
+                    Err(e) => Err(e.into()),
+                },
+                None => handle(stream, &routes, &policy).await,
+            };
+            if let Err(e) = result {
+                tracing::debug!("Gateway connection from {peer} ended: {e:#}");
+            }
+        });
+    }
+}
+
+async fn handle<S>(mut client: S, routes: &Routes, policy: &Policy) -> anyhow::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let head = net::read_head(&mut client).await?;
+    // The permit, if any, is held until the relay ends
+    let (forward, _permit, route) = match screen(&mut client, &head, policy).await? {
+        Admission::Forward { request, permit, route } => (request, permit, route),
+        Admission::Reject { status, reason } => {
+            tracing::info!("Gateway rejected a session request: {reason}");
+            let body = serde_json::json!({ "message": reason }).to_string();
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            client.write_all(response.as_bytes()).await?;
+            client.flush().await?;
+            return Ok(());
+        }
+    };
+
+    let relay = routes.open(&route);
+    let upstream = relay.upstream;
+    let mut server = TcpStream::connect(upstream).await?;
+    server.write_all(&forward).await?;
+    match route {
+        Route::Session => {
+            let response = relay_response(&mut client, &mut server, Vec::new(), false).await?;
+            if let Some(session_id) = issued_session(&response)? {
+                routes.record(session_id, upstream);
+            }
+        }
+        Route::Notarize(_) => {
+            // Any answer but the upgrade ends the connection like that to a plain request
+            let head = net::read_head(&mut server).await?;
+            if net::status_line(&head)?.0 == 101 {
+                client.write_all(&head).await?;
+                tokio::io::copy_bidirectional(&mut client, &mut server).await?;
+            } else {
+                relay_response(&mut client, &mut server, head, false).await?;
+            }
+        }
+        Route::Other { head_request } => {
+            relay_response(&mut client, &mut server, Vec::new(), head_request).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Relays one response from `server` to `client`, of which `buf` was read already,
+/// returning it once it is complete.
+async fn relay_response<C, S>(
+    client: &mut C,
+    server: &mut S,
+    mut buf: Vec<u8>,
+    head_request: bool,
+) -> anyhow::Result<http::Response>
+where
+    C: AsyncWrite + Unpin,
+    S: AsyncRead + Unpin,
+{
+    let mut chunk = [0u8; 4096];
+    let mut reader = http::ResponseReader::new(head_request);
+    let mut eof = false;
+    let response = loop {
+        if reader.update(&buf, eof)? {
+            break reader.into_response()?;
+        }
+        if eof {
+            anyhow::bail!("notary server closed the connection before responding");
+        }
+        if buf.len() > MAX_RESPONSE {
+            anyhow::bail!("notary server response exceeds {MAX_RESPONSE} bytes");
+        }
+        let n = server.read(&mut chunk).await?;
+        buf.extend_from_slice(&chunk[..n]);
+        eof = n == 0;
+    };
+    client.write_all(&buf).await?;
+    client.flush().await?;
+    Ok(response)
+}
+
+/// Returns the id of the session a response to a session request issued, if any.
+fn issued_session(response: &http::Response) -> anyhow::Result<Option<String>> {
+    if response.status != 200 {
+        return Ok(None);
+    }
+    let session: serde_json::Value = serde_json::from_slice(&response.decoded_body()?)?;
+    Ok(session["sessionId"].as_str().map(str::to_string))
+}
+
+enum Admission {
+    /// Forward `request`, then relay the connection, holding the notarization's
+    /// permit if it is one.
+    Forward { request: Vec<u8>, permit: Option<OwnedSemaphorePermit>, route: Route },
+    /// Answer with `status` (e.g. `401 Unauthorized`) and close.
+    Reject { status: &'static str, reason: String },
+}
+
+/// What an admitted request is, as far as choosing its server goes.
+enum Route {
+    /// `POST /session`, answered by the current server.
+    Session,
+    /// `/notarize` for the session with this id, answered by the server that issued it.
+    Notarize(Option<String>),
+    /// Anything else, answered by the current server; `HEAD` requests get no body.
+    Other { head_request: bool },
+}
+
+/// Decides whether the request with `head` may reach the notary server.
+///
+/// Session requests must carry a whitelisted API key, if there is a whitelist, and have
+/// their body checked against the client's ceiling; missing limits are filled in with
+/// the ceiling so the upstream server's higher limits never apply. Notarization
+/// upgrades take a permit from `sessions`, and session requests are turned away while
+/// there is none left, so clients learn early that the notary is busy. Other requests
+/// (`/info`) pass unchecked. Everything but notarization upgrades is rebuilt with
+/// `Connection: close`.
+async fn screen<S>(client: &mut S, head: &[u8], policy: &Policy) -> anyhow::Result<Admission>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut request = httparse::Request::new(&mut headers);
+    request.parse(head)?;
+
+    let (route, query) = match request.path {
+        Some(path) => path.split_once('?').unwrap_or((path, "")),
+        None => ("", ""),
+    };
+    if route == "/notarize" {
+        let session_id = url::form_urlencoded::parse(query.as_bytes())
+            .find(|(name, _)| name == "sessionId")
+            .map(|(_, id)| id.into_owned());
+        return Ok(match policy.sessions.clone().try_acquire_owned() {
+            Ok(permit) => Admission::Forward {
+                request: head.to_vec(),
+                permit: Some(permit),
+                route: Route::Notarize(session_id),
+            },
+            Err(_) => policy.busy(),
+        });
+    }
+
+    let header = |name: &str| {
+        request
+            .headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .and_then(|header| std::str::from_utf8(header.value).ok())
+    };
+    if header("transfer-encoding").is_some() {
+        anyhow::bail!("request bodies must be framed by Content-Length");
+    }
+    let content_length: usize = header("content-length").unwrap_or("0").trim().parse()?;
+    if content_length > MAX_REQUEST_BODY {
+        anyhow::bail!("request body exceeds {MAX_REQUEST_BODY} bytes");
+    }
+    let mut body = vec![0u8; content_length];
+    client.read_exact(&mut body).await?;
+
+    let is_session = request.method == Some("POST") && route == "/session";
+    if !is_session {
+        let head_request = request.method == Some("HEAD");
+        return Ok(Admission::Forward {
+            request: closing_request(&request, &body)?,
+            permit: None,
+            route: Route::Other { head_request },
+        });
+    }
+
+    let api_key = header("authorization").map(str::trim);
+    if let Some(whitelist) = &policy.whitelist {
+        if !api_key.is_some_and(|key| whitelist.contains(key)) {
+            return Ok(Admission::Reject {
+                status: "401 Unauthorized",
+                reason: "Unauthorized request: missing or invalid API key".to_string(),
+            });
+        }
+    }
+
+    if policy.sessions.available_permits() == 0 {
+        return Ok(policy.busy());
+    }
+
+    let limits = policy.limits_for(api_key);
+    let mut config: serde_json::Value = serde_json::from_slice(&body)?;
+    if !config.is_object() {
+        anyhow::bail!("session request body is not a JSON object");
+    }
+    for (field, ceiling) in [
+        ("maxSentData", limits.max_sent_data),
+        ("maxRecvData", limits.max_recv_data),
+    ] {
+        match config[field].as_u64() {
+            None => config[field] = ceiling.into(),
+            Some(requested) if requested > ceiling as u64 => {
+                return Ok(Admission::Reject {
+                    status: "413 Payload Too Large",
+                    reason: format!(
+                        "{field} of {requested} exceeds this client's limit of {ceiling}"
+                    ),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Rebuilt so the new body is framed correctly
+    let body = serde_json::to_vec(&config)?;
+    Ok(Admission::Forward {
+        request: closing_request(&request, &body)?,
+        permit: None,
+        route: Route::Session,
+    })
+}
+
+/// Rebuilds `request` with `body` and `Connection: close`, so the notary server answers
+/// it and nothing after it on the connection.
+fn closing_request(request: &httparse::Request<'_, '_>, body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let headers: Vec<(String, String)> = request
+        .headers
+        .iter()
+        .filter(|header| {
+            !header.name.eq_ignore_ascii_case("content-length")
+                && !header.name.eq_ignore_ascii_case("connection")
+        })
+        .map(|header| {
+            (header.name.to_string(), String::from_utf8_lossy(header.value).into_owned())
+        })
+        .chain([("Connection".to_string(), "close".to_string())])
+        .collect();
+    let method = request.method.unwrap_or("GET");
+    let path = request.path.unwrap_or("/");
+    http::build_request(method, path, &headers, (!body.is_empty()).then_some(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const INFO: &[u8] = b"GET /info HTTP/1.1\r\nHost: notary\r\n\r\n";
+    const FORWARDED_INFO: &[u8] =
+        b"GET /info HTTP/1.1\r\nHost: notary\r\nConnection: close\r\n\r\n";
+    const INFO_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+
+    fn limits() -> Limits {
+        Limits { max_sent_data: 4096, max_recv_data: 16384 }
+    }
+
+    fn session_request(api_key: Option<&str>, body: &str) -> Vec<u8> {
+        let mut request = String::from("POST /session HTTP/1.1\r\nHost: notary\r\n");
+        if let Some(api_key) = api_key {
+            request.push_str(&format!("Authorization: {api_key}\r\n"));
+        }
+        request.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+        request.into_bytes()
+    }
+
+    /// Runs a notary server stand-in that answers the first request on a connection with
+    /// `response` and returns everything it received on it.
+    async fn upstream(response: &'static [u8]) -> (SocketAddr, tokio::task::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = net::read_head(&mut stream).await.unwrap();
+            stream.write_all(response).await.unwrap();
+            stream.read_to_end(&mut received).await.unwrap();
+            received
+        });
+        (addr, received)
+    }
+
+    /// Sends `requests` on one gateway connection, returning what the client got back and
+    /// what the notary server received.
+    async fn exchange(
+        requests: &[u8],
+        policy: &Policy,
+        response: &'static [u8],
+    ) -> (Vec<u8>, Vec<u8>) {
+        let (addr, received) = upstream(response).await;
+        let (mut client, gateway) = tokio::io::duplex(64 * 1024);
+        client.write_all(requests).await.unwrap();
+        client.shutdown().await.unwrap();
+        handle(gateway, &Routes::new(addr), policy).await.unwrap();
+
+        let mut answer = Vec::new();
+        client.read_to_end(&mut answer).await.unwrap();
+        (answer, received.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn a_session_request_after_another_request_is_not_forwarded_unscreened() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "Name,ApiKey,CreatedAt\nalice,secret,2025-01-01T00:00:00Z").unwrap();
+        let whitelist = Whitelist::load(file.path().to_str().unwrap()).unwrap();
+        let policy = Policy { whitelist: Some(whitelist), ..Policy::open(limits()) };
+
+        let requests = [INFO, &session_request(None, "{}")].concat();
+        let (answer, received) = exchange(&requests, &policy, INFO_RESPONSE).await;
+        assert_eq!(answer, INFO_RESPONSE);
+        assert_eq!(received, FORWARDED_INFO);
+    }
+
+    #[tokio::test]
+    async fn tenant_ceilings_hold_for_a_second_request_on_a_connection() {
+        let tenants = HashMap::from([("tenant".to_string(), limits())]);
+        let policy = Policy { tenant_limits: tenants, ..Policy::open(limits()) };
+
+        let oversized = session_request(Some("tenant"), r#"{"maxSentData":999999}"#);
+        let requests = [INFO, &oversized].concat();
+        let (answer, received) = exchange(&requests, &policy, INFO_RESPONSE).await;
+        assert_eq!(answer, INFO_RESPONSE);
+        assert_eq!(received, FORWARDED_INFO);
+    }
+
+    const NOTARIZE: &[u8] = b"GET /notarize?sessionId=a HTTP/1.1\r\nHost: notary\r\n\
+        Connection: Upgrade\r\nUpgrade: TCP\r\n\r\n";
+
+    #[tokio::test]
+    async fn a_connection_admitted_for_another_request_cannot_upgrade() {
+        let policy = Policy::open(limits());
+
+        let requests = [INFO, NOTARIZE].concat();
+        let (answer, received) = exchange(&requests, &policy, INFO_RESPONSE).await;
+        assert_eq!(answer, INFO_RESPONSE);
+        assert_eq!(received, FORWARDED_INFO);
+    }
+
+    #[tokio::test]
+    async fn a_refused_upgrade_ends_the_connection() {
+        const REFUSED: &[u8] = b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n";
+        let policy = Policy::open(limits());
+
+        let requests = [NOTARIZE, INFO].concat();
+        let (answer, received) = exchange(&requests, &policy, REFUSED).await;
+        assert_eq!(answer, REFUSED);
+        assert_eq!(received, NOTARIZE);
+        assert_eq!(policy.active_sessions(), 0);
+    }
+
+    #[tokio::test]
+    async fn an_accepted_upgrade_is_relayed_as_is() {
+        const UPGRADED: &[u8] =
+            b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: TCP\r\n\r\n";
+        let policy = Policy::open(limits());
+
+        let requests = [NOTARIZE, b"mpc", INFO].concat();
+        let (answer, received) = exchange(&requests, &policy, UPGRADED).await;
+        assert_eq!(answer, UPGRADED);
+        assert_eq!(received, requests);
+    }
+}
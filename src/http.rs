@@ -0,0 +1,640 @@
+//! Minimal HTTP/1.1 framing used to talk to the server over the MPC-TLS connection.
+//!
+//! Only the framing needed to know when a response is complete is implemented; the
+//! bytes written and read are exactly the ones that end up in the transcript.
+
+use std::io::Read;
+use std::ops::Range;
+
+use anyhow::Context;
+
+/// Maximum number of response headers accepted from the server.
+const MAX_HEADERS: usize = 64;
+/// Upper bound on a decompressed body, guarding against compression bombs.
+const MAX_DECOMPRESSED_BODY: u64 = 64 * 1024 * 1024;
+/// Upper bound on a chunk size or trailer line read by `BodyDecoder`.
+const MAX_FRAMING_LINE: usize = 8192;
+
+/// A parsed HTTP response.
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Response {
+    /// Returns the value of the first header named `name`, compared case-insensitively.
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the body with the `Content-Encoding` the server applied undone.
+    ///
+    /// `gzip` and `deflate` (zlib-wrapped or raw) are decoded; bodies with any other
+    /// coding are returned as received.
+    pub(crate) fn decoded_body(&self) -> anyhow::Result<Vec<u8>> {
+        let codings: Vec<String> = self
+            .header("Content-Encoding")
+            .unwrap_or_default()
+            .split(',')
+            .map(|coding| coding.trim().to_ascii_lowercase())
+            .filter(|coding| !coding.is_empty() && coding != "identity")
+            .collect();
+        let supported = |coding: &String| matches!(coding.as_str(), "gzip" | "x-gzip" | "deflate");
+        if !codings.iter().all(supported) {
+            return Ok(self.body.clone());
+        }
+
+        // Codings are listed in the order they were applied
+        let mut body = self.body.clone();
+        for coding in codings.iter().rev() {
+            body = match coding.as_str() {
+                "deflate" => inflate(&body)?,
+                _ => read_limited(flate2::read::MultiGzDecoder::new(&body[..]))
+                    .context("invalid gzip body")?,
+            };
+        }
+        Ok(body)
+    }
+}
+
+/// Decodes a `deflate` body, which servers send either zlib-wrapped, as specified, or raw.
+fn inflate(body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    read_limited(flate2::read::ZlibDecoder::new(body))
+        .or_else(|_| read_limited(flate2::read::DeflateDecoder::new(body)))
+        .context("invalid deflate body")
+}
+
+fn read_limited(decoder: impl Read) -> anyhow::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    decoder.take(MAX_DECOMPRESSED_BODY + 1).read_to_end(&mut body)?;
+    if body.len() as u64 > MAX_DECOMPRESSED_BODY {
+        anyhow::bail!("decompressed body exceeds {MAX_DECOMPRESSED_BODY} bytes");
+    }
+    Ok(body)
+}
+
+/// Adds `Host: <host>` and `Connection: close` unless `headers` already set them.
+///
+/// `Host` goes first, as most servers expect; `Connection` is appended.
+pub(crate) fn with_default_headers(
+    mut headers: Vec<(String, String)>,
+    host: &str,
+) -> Vec<(String, String)> {
+    let has = |headers: &[(String, String)], name: &str| {
+        headers.iter().any(|(key, _)| key.eq_ignore_ascii_case(name))
+    };
+
+    if !has(&headers, "Host") {
+        headers.insert(0, ("Host".to_string(), host.to_string()));
+    }
+    if !has(&headers, "Connection") {
+        headers.push(("Connection".to_string(), "close".to_string()));
+    }
+    headers
+}
+
+/// Checks a caller's path for use as the request target, returning it unchanged.
+///
+/// The path is written byte for byte, since those bytes are what gets notarized: query
+/// strings, fragments and percent-encoding are kept as given and nothing is re-encoded.
+/// An empty path becomes `/`. Whitespace and control characters are rejected, as they
+/// would end the request line early or smuggle in headers.
+pub(crate) fn request_target(path: &str) -> anyhow::Result<&str> {
+    if path.is_empty() {
+        return Ok("/");
+    }
+    if let Some(c) = path.chars().find(|c| c.is_whitespace() || c.is_control()) {
+        anyhow::bail!("contains {c:?}; percent-encode it");
+    }
+    Ok(path)
+}
+
+/// Serializes a request line, header block and optional body.
+///
+/// A `Content-Length` header is added for the body unless the caller already framed
+/// it with `Content-Length` or `Transfer-Encoding`. The method and header names must be
+/// tokens and header values free of control characters other than tab, so none of
+/// them can end a line early and inject headers.
+pub(crate) fn build_request(
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+) -> anyhow::Result<Vec<u8>> {
+    if !is_token(method) {
+        anyhow::bail!("invalid method {method:?}");
+    }
+    for (name, value) in headers {
+        if !is_token(name) {
+            anyhow::bail!("invalid header name {name:?}");
+        }
+        if let Some(c) = value.chars().find(|&c| c != '\t' && c.is_control()) {
+            anyhow::bail!("header {name} contains {c:?}");
+        }
+    }
+
+    let mut request = format!("{method} {path} HTTP/1.1\r\n").into_bytes();
+    for (name, value) in headers {
+        request.extend_from_slice(format!("{name}: {value}\r\n").as_bytes());
+    }
+    if let Some(body) = body {
+        let framed = headers.iter().any(|(name, _)| {
+            name.eq_ignore_ascii_case("Content-Length")
+                || name.eq_ignore_ascii_case("Transfer-Encoding")
+        });
+        if !framed {
+            request.extend_from_slice(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+        }
+    }
+    request.extend_from_slice(b"\r\n");
+    if let Some(body) = body {
+        request.extend_from_slice(body);
+    }
+    Ok(request)
+}
+
+/// Whether `s` is an HTTP token (RFC 9110), as methods and header names must be.
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Parses `buf`, everything the server sent, as an HTTP response to a request that was
+/// a `HEAD` request if `head_request` is set.
+///
+/// Returns `None` if the head hasn't fully arrived, and fails if the body hasn't.
+pub(crate) fn parse_response(buf: &[u8], head_request: bool) -> anyhow::Result<Option<Response>> {
+    let mut reader = ResponseReader::new(head_request);
+    if !reader.update(buf, true)? {
+        return Ok(None);
+    }
+    reader.into_response().map(Some)
+}
+
+/// Reads an HTTP response from bytes arriving piece by piece.
+///
+/// Interim (1xx) responses before it are skipped. Responses to `HEAD` requests and with
+/// status 204 or 304 have no body; other bodies end as their `Content-Length` or chunked
+/// framing says, or else when the server closes the connection. Once the head has been
+/// parsed, each byte of the body is only looked at once.
+pub(crate) struct ResponseReader {
+    head_request: bool,
+    /// The final response, with the body decoded so far, once its head has arrived.
+    response: Option<(Response, BodyDecoder)>,
+    /// How many of the bytes received were parsed already.
+    consumed: usize,
+}
+
+impl ResponseReader {
+    pub(crate) fn new(head_request: bool) -> Self {
+        Self { head_request, response: None, consumed: 0 }
+    }
+
+    /// Takes everything received so far, a prefix of which earlier calls were given,
+    /// and returns whether the response is complete. With `eof`, the server closed the
+    /// connection after `buf`, which fails if it ended the body early.
+    pub(crate) fn update(&mut self, buf: &[u8], eof: bool) -> anyhow::Result<bool> {
+        if self.response.is_none() {
+            let Some((response, body_start)) = parse_final_head(buf)? else {
+                return Ok(false);
+            };
+            let decoder = BodyDecoder::new(&response, self.head_request)?;
+            self.response = Some((response, decoder));
+            self.consumed = body_start;
+        }
+
+        let (response, decoder) = self.response.as_mut().expect("the head has arrived");
+        decoder.feed(&buf[self.consumed..], &mut response.body)?;
+        self.consumed = buf.len();
+        if eof {
+            decoder.finish()?;
+            return Ok(true);
+        }
+        Ok(decoder.is_complete())
+    }
+
+    /// Returns the response once `update` has returned true.
+    pub(crate) fn into_response(self) -> anyhow::Result<Response> {
+        match self.response {
+            Some((response, _)) => Ok(response),
+            None => anyhow::bail!("incomplete response"),
+        }
+    }
+}
+
+/// Whether `request` is a `HEAD` request, whose response has no body.
+pub(crate) fn is_head_request(request: &[u8]) -> bool {
+    request.starts_with(b"HEAD ")
+}
+
+/// Whether `status` is that of an interim response, which a final one follows. `101
+/// Switching Protocols` is final, as the connection then carries another protocol.
+fn is_interim(status: u16) -> bool {
+    matches!(status, 100 | 102..=199)
+}
+
+/// Parses the head of the final response in `buf`, skipping interim responses before
+/// it, and returns it with the offset its body starts at. Returns `None` until that
+/// head is complete.
+pub(crate) fn parse_final_head(buf: &[u8]) -> anyhow::Result<Option<(Response, usize)>> {
+    let mut start = 0;
+    loop {
+        let Some((response, head_len)) = parse_head(&buf[start..])? else {
+            return Ok(None);
+        };
+        start += head_len;
+        if !is_interim(response.status) {
+            return Ok(Some((response, start)));
+        }
+    }
+}
+
+/// Parses a response head, returning the response without a body and the length of the
+/// head. Returns `None` until the head is complete.
+pub(crate) fn parse_head(buf: &[u8]) -> anyhow::Result<Option<(Response, usize)>> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut parsed = httparse::Response::new(&mut headers);
+    let header_len = match parsed.parse(buf).context("malformed response head")? {
+        httparse::Status::Complete(len) => len,
+        httparse::Status::Partial => return Ok(None),
+    };
+
+    let status = parsed.code.context("missing status code")?;
+    let headers = parsed
+        .headers
+        .iter()
+        .map(|h| (h.name.to_string(), String::from_utf8_lossy(h.value).into_owned()))
+        .collect();
+    Ok(Some((Response { status, headers, body: Vec::new() }, header_len)))
+}
+
+/// Decodes a response body piece by piece as it arrives, so it needn't be held in
+/// memory as a whole. Chunked framing is removed; content codings are kept.
+pub(crate) struct BodyDecoder {
+    framing: Framing,
+}
+
+enum Framing {
+    /// The given number of body bytes are still to come.
+    Length(usize),
+    Chunked { state: ChunkState, line: Vec<u8> },
+    /// The body ends when the server closes the connection.
+    UntilEof,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ChunkState {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+impl BodyDecoder {
+    /// Prepares to decode the body of `response`, as framed by its status and headers,
+    /// or to expect none if it answers a `HEAD` request.
+    pub(crate) fn new(response: &Response, head_request: bool) -> anyhow::Result<Self> {
+        let chunked = response
+            .header("Transfer-Encoding")
+            .is_some_and(|te| te.to_ascii_lowercase().contains("chunked"));
+        let framing = if head_request || matches!(response.status, 100..=199 | 204 | 304) {
+            Framing::Length(0)
+        } else if chunked {
+            return Ok(Self::chunked());
+        } else if let Some(len) = response.header("Content-Length") {
+            Framing::Length(len.trim().parse().context("invalid Content-Length")?)
+        } else {
+            Framing::UntilEof
+        };
+        Ok(Self { framing })
+    }
+
+    /// Prepares to decode a chunked body.
+    fn chunked() -> Self {
+        Self { framing: Framing::Chunked { state: ChunkState::Size, line: Vec::new() } }
+    }
+
+    /// Whether the whole body has been decoded. Bodies read until EOF never are.
+    pub(crate) fn is_complete(&self) -> bool {
+        match &self.framing {
+            Framing::Length(remaining) => *remaining == 0,
+            Framing::Chunked { state, .. } => *state == ChunkState::Done,
+            Framing::UntilEof => false,
+        }
+    }
+
+    /// Checks that the body may end where the server closed the connection.
+    pub(crate) fn finish(&self) -> anyhow::Result<()> {
+        if !self.is_complete() && !matches!(self.framing, Framing::UntilEof) {
+            anyhow::bail!("connection closed before the response body was complete");
+        }
+        Ok(())
+    }
+
+    /// Decodes the next bytes received, appending body bytes to `out`. Bytes after the
+    /// end of the body are ignored; the number of bytes consumed is returned.
+    pub(crate) fn feed(&mut self, mut data: &[u8], out: &mut Vec<u8>) -> anyhow::Result<usize> {
+        let total = data.len();
+        match &mut self.framing {
+            Framing::Length(remaining) => {
+                let n = data.len().min(*remaining);
+                out.extend_from_slice(&data[..n]);
+                *remaining -= n;
+                data = &data[n..];
+            }
+            Framing::UntilEof => {
+                out.extend_from_slice(data);
+                data = &[];
+            }
+            Framing::Chunked { state, line } => {
+                while !data.is_empty() && *state != ChunkState::Done {
+                    if let ChunkState::Data(remaining) = *state {
+                        let n = data.len().min(remaining);
+                        out.extend_from_slice(&data[..n]);
+                        data = &data[n..];
+                        *state = match remaining - n {
+                            0 => ChunkState::DataEnd,
+                            left => ChunkState::Data(left),
+                        };
+                        continue;
+                    }
+
+                    // The other states consume one CRLF-terminated line at a time
+                    let Some(end) = data.iter().position(|&b| b == b'\n') else {
+                        line.extend_from_slice(data);
+                        data = &[];
+                        if line.len() > MAX_FRAMING_LINE {
+                            anyhow::bail!("chunk framing line exceeds {MAX_FRAMING_LINE} bytes");
+                        }
+                        break;
+                    };
+                    line.extend_from_slice(&data[..=end]);
+                    data = &data[end + 1..];
+                    let Some(text) = line.strip_suffix(b"\r\n") else {
+                        anyhow::bail!("chunk framing line is not terminated by CRLF");
+                    };
+
+                    *state = match *state {
+                        ChunkState::Size => {
+                            let text = std::str::from_utf8(text).context("invalid chunk size")?;
+                            // Chunk extensions after `;` are ignored.
+                            let size = text.split(';').next().unwrap_or_default().trim();
+                            match usize::from_str_radix(size, 16).context("invalid chunk size")? {
+                                0 => ChunkState::Trailers,
+                                size => ChunkState::Data(size),
+                            }
+                        }
+                        ChunkState::DataEnd if text.is_empty() => ChunkState::Size,
+                        ChunkState::DataEnd => anyhow::bail!("chunk is not terminated by CRLF"),
+                        // Trailers are skipped up to the empty line ending the body
+                        _ if text.is_empty() => ChunkState::Done,
+                        other => other,
+                    };
+                    line.clear();
+                }
+            }
+        }
+        Ok(total - data.len())
+    }
+}
+
+/// Splits a transcript direction into its HTTP messages, returning the byte ranges of
+/// each message's head (through the blank line) and body (as framed on the wire).
+///
+/// Requests and responses are both understood, including several messages sent over
+/// one connection. A message whose body isn't delimited by `Content-Length` or chunked
+/// encoding extends to the end of the data.
+pub(crate) fn message_ranges(data: &[u8]) -> anyhow::Result<Vec<(Range<usize>, Range<usize>)>> {
+    let mut messages = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let buf = &data[start..];
+        let head_len = find_header_end(buf)
+            .map(|end| end + 4)
+            .ok_or_else(|| anyhow::anyhow!("HTTP message at byte {start} has no complete head"))?;
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let header = |headers: &[httparse::Header<'_>], name: &str| {
+            headers
+                .iter()
+                .find(|header| header.name.eq_ignore_ascii_case(name))
+                .map(|header| String::from_utf8_lossy(header.value).to_ascii_lowercase())
+        };
+        let (headers, bodyless, is_response) = if buf.starts_with(b"HTTP/") {
+            let mut response = httparse::Response::new(&mut headers);
+            response.parse(buf).context("malformed response head")?;
+            let bodyless = matches!(response.code, Some(100..=199 | 204 | 304));
+            (response.headers, bodyless, true)
+        } else {
+            let mut request = httparse::Request::new(&mut headers);
+            request.parse(buf).context("malformed request head")?;
+            (request.headers, false, false)
+        };
+
+        let rest = &buf[head_len..];
+        let chunked = header(headers, "transfer-encoding").is_some_and(|te| te.contains("chunked"));
+        let body_len = if bodyless {
+            0
+        } else if chunked {
+            let mut decoder = BodyDecoder::chunked();
+            let len = decoder.feed(rest, &mut Vec::new())?;
+            if decoder.is_complete() {
+                len
+            } else {
+                rest.len()
+            }
+        } else if let Some(len) = header(headers, "content-length") {
+            let len: usize = len.trim().parse().context("invalid Content-Length")?;
+            len.min(rest.len())
+        } else if is_response {
+            rest.len()
+        } else {
+            0
+        };
+
+        let head = start..start + head_len;
+        let body = head.end..head.end + body_len;
+        start = body.end;
+        messages.push((head, body));
+    }
+    Ok(messages)
+}
+
+fn find_header_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_target_keeps_query_strings() {
+        assert_eq!(request_target("/search?q=a&page=2").unwrap(), "/search?q=a&page=2");
+        assert_eq!(request_target("/?").unwrap(), "/?");
+    }
+
+    #[test]
+    fn request_target_keeps_percent_encoding() {
+        assert_eq!(request_target("/a%20b/%E2%9C%93?x=%2F").unwrap(), "/a%20b/%E2%9C%93?x=%2F");
+        // Not normalized: lower-case and unpaired escapes are passed on as given
+        assert_eq!(request_target("/%e2%9c%93%").unwrap(), "/%e2%9c%93%");
+    }
+
+    #[test]
+    fn request_target_defaults_to_root() {
+        assert_eq!(request_target("").unwrap(), "/");
+    }
+
+    #[test]
+    fn request_target_rejects_whitespace_and_control_characters() {
+        for path in ["/a b", "/a\tb", "/a\r\nHost: evil", "/a\n", "/a\0", "/a\u{7f}"] {
+            assert!(request_target(path).is_err(), "{path:?} was accepted");
+        }
+    }
+
+    fn header(name: &str, value: &str) -> Vec<(String, String)> {
+        vec![(name.to_string(), value.to_string())]
+    }
+
+    #[test]
+    fn build_request_rejects_line_breaks() {
+        assert!(build_request("GET", "/", &header("X-Test", "a\r\nEvil: 1"), None).is_err());
+        assert!(build_request("GET", "/", &header("X-Test", "a\nb"), None).is_err());
+        assert!(build_request("GET", "/", &header("X-Test", "a\0b"), None).is_err());
+        assert!(build_request("GET", "/", &header("X-Test\r\nEvil", "1"), None).is_err());
+        assert!(build_request("GET", "/", &header("", "1"), None).is_err());
+        assert!(build_request("GET /evil HTTP/1.1\r\n", "/", &[], None).is_err());
+    }
+
+    #[test]
+    fn build_request_keeps_valid_heads() {
+        let request = build_request("POST", "/a", &header("X-Test", "a\tb"), Some(b"hi")).unwrap();
+        assert_eq!(request, b"POST /a HTTP/1.1\r\nX-Test: a\tb\r\nContent-Length: 2\r\n\r\nhi");
+    }
+
+    fn response_head(head: &[u8]) -> Response {
+        parse_head(head).unwrap().expect("the head is complete").0
+    }
+
+    /// Feeds `data` to `decoder` `step` bytes at a time, returning the body decoded.
+    fn decode(decoder: &mut BodyDecoder, data: &[u8], step: usize) -> Vec<u8> {
+        let mut body = Vec::new();
+        for piece in data.chunks(step) {
+            decoder.feed(piece, &mut body).unwrap();
+        }
+        body
+    }
+
+    const CHUNKED: &[u8] = b"4;ext=1\r\nWiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\n\r\n";
+
+    #[test]
+    fn body_decoder_removes_chunked_framing_in_any_pieces() {
+        let response = response_head(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+        for step in [1, 2, 7, CHUNKED.len()] {
+            let mut decoder = BodyDecoder::new(&response, false).unwrap();
+            assert_eq!(decode(&mut decoder, CHUNKED, step), b"Wikipedia");
+            assert!(decoder.is_complete());
+        }
+    }
+
+    #[test]
+    fn body_decoder_reports_the_bytes_it_consumed() {
+        let mut decoder = BodyDecoder::chunked();
+        let mut data = CHUNKED.to_vec();
+        data.extend_from_slice(b"HTTP/1.1");
+        assert_eq!(decoder.feed(&data, &mut Vec::new()).unwrap(), CHUNKED.len());
+        assert!(decoder.is_complete());
+    }
+
+    #[test]
+    fn message_ranges_frames_chunked_bodies() {
+        let head = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let mut data = [&head[..], CHUNKED].concat();
+        let first = head.len()..head.len() + CHUNKED.len();
+        data.extend_from_slice(b"HTTP/1.1 204 No Content\r\n\r\n");
+        let messages = message_ranges(&data).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0], (0..head.len(), first.clone()));
+        assert_eq!(messages[1].0, first.end..data.len());
+
+        // A chunked body cut short extends to the end of the data
+        let truncated = [&head[..], &CHUNKED[..10]].concat();
+        let messages = message_ranges(&truncated).unwrap();
+        assert_eq!(messages, vec![(0..head.len(), head.len()..truncated.len())]);
+
+        assert!(message_ranges(&[&head[..], b"4\r\nWikiXX0\r\n\r\n"].concat()).is_err());
+        assert!(message_ranges(&[&head[..], b"zz\r\n"].concat()).is_err());
+    }
+
+    #[test]
+    fn body_decoder_stops_at_content_length() {
+        let response = response_head(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n");
+        let mut decoder = BodyDecoder::new(&response, false).unwrap();
+        assert_eq!(decode(&mut decoder, b"hel", 1), b"hel");
+        assert!(!decoder.is_complete());
+        assert!(decoder.finish().is_err());
+
+        assert_eq!(decode(&mut decoder, b"loHTTP/1.1", 10), b"lo");
+        assert!(decoder.is_complete());
+    }
+
+    #[test]
+    fn body_decoder_reads_unframed_bodies_until_eof() {
+        let response = response_head(b"HTTP/1.1 200 OK\r\n\r\n");
+        let mut decoder = BodyDecoder::new(&response, false).unwrap();
+        assert_eq!(decode(&mut decoder, b"abc", 1), b"abc");
+        assert!(!decoder.is_complete());
+        assert!(decoder.finish().is_ok());
+    }
+
+    #[test]
+    fn body_decoder_expects_no_body_for_head_204_and_304() {
+        let ok = response_head(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n");
+        assert!(BodyDecoder::new(&ok, true).unwrap().is_complete());
+        for head in [
+            &b"HTTP/1.1 204 No Content\r\nContent-Length: 5\r\n\r\n"[..],
+            b"HTTP/1.1 304 Not Modified\r\nTransfer-Encoding: chunked\r\n\r\n",
+        ] {
+            assert!(BodyDecoder::new(&response_head(head), false).unwrap().is_complete());
+        }
+    }
+
+    #[test]
+    fn response_reader_skips_interim_responses() {
+        let data = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        let mut reader = ResponseReader::new(false);
+        for end in 0..data.len() {
+            assert!(!reader.update(&data[..end], false).unwrap(), "complete at {end}");
+        }
+        assert!(reader.update(data, false).unwrap());
+        let response = reader.into_response().unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"ok");
+    }
+
+    #[test]
+    fn response_reader_ends_head_responses_with_the_head() {
+        let mut reader = ResponseReader::new(true);
+        assert!(reader.update(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n", false).unwrap());
+        assert!(reader.into_response().unwrap().body.is_empty());
+
+        assert!(is_head_request(b"HEAD / HTTP/1.1\r\n\r\n"));
+        assert!(!is_head_request(b"GET /HEAD HTTP/1.1\r\n\r\n"));
+    }
+
+    #[test]
+    fn parse_response_needs_the_whole_body() {
+        let truncated = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhi";
+        assert!(parse_response(truncated, false).is_err());
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-", false).unwrap().is_none());
+
+        let response = parse_response(b"HTTP/1.1 200 OK\r\n\r\nuntil eof", false).unwrap().unwrap();
+        assert_eq!(response.body, b"until eof");
+    }
+}
@@ -0,0 +1,59 @@
+//! Queries a notary's `/info` endpoint without starting a notarization.
+
+use std::time::Duration;
+
+use tokio_rustls::rustls::RootCertStore;
+
+use crate::{http, net};
+
+/// Upper bound on the size of an `/info` response.
+const MAX_INFO_RESPONSE: usize = 64 * 1024;
+
+/// Fetches the notary's `/info` document, returning the JSON body.
+///
+/// `root_store` overrides the webpki roots used to verify the notary over TLS.
+pub(crate) async fn fetch(
+    host: &str,
+    port: u16,
+    tls: bool,
+    root_store: Option<RootCertStore>,
+    timeout: Duration,
+) -> anyhow::Result<String> {
+    let request = http::build_request(
+        "GET",
+        "/info",
+        &http::with_default_headers(vec![("Accept".into(), "application/json".into())], host),
+        None,
+    )?;
+
+    let response = tokio::time::timeout(timeout, async {
+        if tls {
+            let mut stream = net::connect_tls(host, port, host, root_store).await?;
+            net::round_trip(&mut stream, &request, MAX_INFO_RESPONSE).await
+        } else {
+            let mut stream = net::connect_tcp(host, port, false).await?;
+            net::round_trip(&mut stream, &request, MAX_INFO_RESPONSE).await
+        }
+    })
+    .await
+    .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "notary info request timed out"))??;
+
+    if response.status != 200 {
+        anyhow::bail!("notary answered /info with status {}", response.status);
+    }
+    Ok(String::from_utf8(response.body)?)
+}
+
+/// Converts a camelCase key from the notary's JSON to snake_case.
+pub(crate) fn snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
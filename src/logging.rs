@@ -0,0 +1,222 @@
+//! Forwards `tracing` events from the prover and notary into Python's `logging`, or
+//! into a size-rotated log file.
+//!
+//! Events are emitted on Tokio worker threads while the calling Python thread may hold
+//! the GIL inside `block_on`, so they are queued and handed to Python by a dedicated
+//! thread instead of acquiring the GIL in place.
+
+use std::fmt::{self, Write};
+use std::fs::{File, OpenOptions};
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::{reload, Layer, Registry};
+
+use crate::errors::TlsnError;
+
+/// Name of the Python logger events are forwarded to; targets become child loggers.
+const LOGGER_NAME: &str = "tlsnpy";
+
+/// Python has no TRACE level, so trace events are logged just below DEBUG.
+const PY_TRACE: u8 = 5;
+
+static LEVEL_HANDLE: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+/// When set, events are written here instead of being forwarded to Python.
+static LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+
+/// Parses a standard level name (`trace`, `debug`, `info`, `warn`, `error`).
+pub(crate) fn parse_level(level: &str) -> PyResult<LevelFilter> {
+    match level.to_ascii_lowercase().as_str() {
+        "trace" => Ok(LevelFilter::TRACE),
+        "debug" => Ok(LevelFilter::DEBUG),
+        "info" => Ok(LevelFilter::INFO),
+        "warn" | "warning" => Ok(LevelFilter::WARN),
+        "error" => Ok(LevelFilter::ERROR),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown log level {level:?}, expected one of trace, debug, info, warn, error"
+        ))),
+    }
+}
+
+/// Installs the forwarding subscriber on first use and sets the process-wide level.
+///
+/// The level is global: the most recently configured instance determines it.
+pub(crate) fn init(level: LevelFilter) -> PyResult<()> {
+    let handle = LEVEL_HANDLE.get_or_init(|| {
+        let (filter, handle) = reload::Layer::new(level);
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || forward(rx));
+
+        let subscriber = Registry::default()
+            .with(filter)
+            .with(PyLoggingLayer { tx: Mutex::new(tx) });
+        // Another subscriber may already be installed by the host application
+        let _ = tracing::subscriber::set_global_default(subscriber);
+        handle
+    });
+
+    handle
+        .reload(level)
+        .map_err(|e| TlsnError::new_err(format!("Failed to set log level: {e}")))
+}
+
+/// Sends events to the file at `path` instead of Python's `logging`, or back to Python
+/// with `None`.
+///
+/// Once the file would grow past `max_bytes` it is renamed to `<path>.1` (shifting
+/// older files up to `<path>.<backups>`, beyond which they are deleted) and a new file
+/// is started. Like the level, this is process-wide.
+pub(crate) fn set_log_file(path: Option<&str>, max_bytes: u64, backups: u32) -> PyResult<()> {
+    let file = path
+        .map(|path| RotatingFile::open(PathBuf::from(path), max_bytes, backups))
+        .transpose()
+        .map_err(|e| crate::io_error(e, path.unwrap_or_default()))?;
+    *LOG_FILE.lock().unwrap_or_else(PoisonError::into_inner) = file;
+    Ok(())
+}
+
+struct Record {
+    target: String,
+    level: Level,
+    message: String,
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    max_bytes: u64,
+    backups: u32,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, backups: u32) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let len = file.metadata()?.len();
+        Ok(Self { path, file, len, max_bytes, backups })
+    }
+
+    fn write(&mut self, record: &Record) -> std::io::Result<()> {
+        let line = format!(
+            "{} {:>5} {}: {}\n",
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            record.level,
+            record.target,
+            record.message
+        );
+        if self.len > 0 && self.len + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.len += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let backup = |n: u32| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{n}"));
+            PathBuf::from(name)
+        };
+        if self.backups == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.backups).rev() {
+                let from = backup(n);
+                if from.exists() {
+                    std::fs::rename(&from, backup(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, backup(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+struct PyLoggingLayer {
+    tx: Mutex<Sender<Record>>,
+}
+
+impl<S: Subscriber> Layer<S> for PyLoggingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        let record = Record {
+            target: metadata.target().to_string(),
+            level: *metadata.level(),
+            message: visitor.finish(),
+        };
+
+        if let Some(file) = LOG_FILE.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+            // Nowhere left to report a failing log file
+            let _ = file.write(&record);
+            return;
+        }
+        if let Ok(tx) = self.tx.lock() {
+            let _ = tx.send(record);
+        }
+    }
+}
+
+fn forward(rx: Receiver<Record>) {
+    for record in rx {
+        Python::with_gil(|py| {
+            // Logging must never raise into unrelated Python code
+            let _ = log(py, &record);
+        });
+    }
+}
+
+fn log(py: Python<'_>, record: &Record) -> PyResult<()> {
+    let level: u8 = match record.level {
+        Level::TRACE => PY_TRACE,
+        Level::DEBUG => 10,
+        Level::INFO => 20,
+        Level::WARN => 30,
+        Level::ERROR => 40,
+    };
+    let name = format!("{LOGGER_NAME}.{}", record.target.replace("::", "."));
+
+    py.import("logging")?
+        .call_method1("getLogger", (name,))?
+        .call_method1("log", (level, record.message.as_str()))?;
+    Ok(())
+}
+
+/// Renders an event as its message followed by `key=value` fields.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(mut self) -> String {
+        self.message.push_str(&self.fields);
+        self.message
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
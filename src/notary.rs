@@ -0,0 +1,999 @@
+//! The notary server: `PyNotary`, and the gateway and upstream servers it runs.
+
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyDict, PyTuple, PyType};
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::runtime::Runtime;
+use tokio::net::TcpStream;
+use tokio::sync::{oneshot, Semaphore};
+use tokio::task::JoinHandle;
+
+use notary_server::{
+    NotaryServerProperties, ServerProperties, NotarizationProperties,
+    TLSProperties, NotarySigningKeyProperties, LoggingProperties,
+    AuthorizationProperties, run_server,
+};
+use tempfile::NamedTempFile;
+
+use crate::errors::{TimeoutError, TlsnError};
+use crate::prover::{DEFAULT_MAX_RECV_DATA, DEFAULT_MAX_SENT_DATA};
+use crate::runtime::PyRuntime;
+use crate::{gateway, io_error, keys, logging, runtime, whitelist};
+
+/// A Python-friendly wrapper around the TLS Notary Server.
+/// 
+/// # Thread Safety
+/// This class is marked as `unsendable`, meaning it cannot be shared between Python threads.
+/// This is necessary because it contains a Tokio runtime which is not `Sync`.
+/// 
+/// # Usage
+/// Create one instance to handle multiple concurrent notarization sessions.
+/// Use start() to begin accepting connections and stop() to gracefully shutdown.
+/// Pass `port=0` to let the OS pick a free port and read it back with bound_port(). The
+/// server's public address is always held by the gateway in front of it (see "Per-tenant
+/// limits"), which binds it once, so no other process can take the port during start().
+///
+/// # Addresses
+/// `host` is the local address to listen on: an IPv4 or IPv6 address of one interface,
+/// `0.0.0.0` or `::` for all of them, or a host name, resolved once by the constructor
+/// (preferring IPv4). Anything else raises ValueError. If the address can't be bound,
+/// e.g. because the port is taken or no interface has that address, `start()` raises
+/// naming it.
+///
+/// # Keys and certificates
+/// The TLS certificate/key and the notary signing keys can be given either as file
+/// paths or as inline PEM bytes (`*_pem` keyword arguments). When both are given for
+/// the same item, the inline PEM takes precedence and the path is ignored. Inline PEM
+/// is written to private temporary files that live as long as the notary.
+///
+/// The signing keys must be a matching secp256k1 or P-256 pair; anything else is
+/// rejected by the constructor rather than surfacing later as invalid signatures.
+/// For local experiments, `PyNotary.dev()` generates all of them (development only).
+///
+/// # Authorization
+/// Passing `authorization_whitelist_csv` enables authorization: only provers sending an
+/// API key listed in that CSV are accepted; others get HTTP 401.
+///
+/// The whitelist can change while the notary runs: `reload_whitelist()` re-reads the
+/// CSV, and `add_token()`/`remove_token()` edit it and apply the change. Each call swaps
+/// the whole key set at once, so a session request is checked against either the old or
+/// the new set, never a mix; sessions already admitted are not affected. The CSV is
+/// rewritten through a temporary file and renamed into place, so other readers never
+/// see a partial file. The whitelist is enforced by the same gateway as per-tenant
+/// limits.
+///
+/// # Per-tenant limits
+/// `tenant_limits` maps API keys to `(max_sent_data, max_recv_data)` ceilings that
+/// replace the global limits for that client; other clients keep the global limits.
+/// Session requests above the client's ceiling are rejected with HTTP 413 and a message
+/// naming the limit. The notary server can't enforce this itself, so the small gateway
+/// the notary listens behind, which also terminates TLS, screens session requests.
+///
+/// # Concurrent sessions
+/// `max_concurrent_sessions` caps the notarizations in progress at once. Clients over
+/// the cap get HTTP 503 ("Notary server busy") when requesting a session or starting to
+/// notarize, rather than being queued. `active_sessions()` reports the current count.
+/// The cap is enforced by the same gateway as per-tenant limits.
+///
+/// # Key rotation
+/// `rotate_signing_key()` replaces the signing keypair. On a stopped notary the new key
+/// is used from the next `start()`. On a running notary it takes effect without
+/// downtime: a second server is started with the new key behind the gateway, and new
+/// sessions go to it while sessions already issued finish on the server that issued
+/// them, signed with the old key. A replaced server is shut down once none of its
+/// sessions is left to notarize or in progress.
+///
+/// Attestations are verified against the key that signed them, so those issued before
+/// the rotation stay verifiable with the old public key; keep publishing it for as long
+/// as they are in use. `public_key_pem()` returns the new one.
+///
+/// # Unix domain sockets
+/// With `unix_socket_path`, the notary listens on that socket instead of `host:port`
+/// (for sidecar deployments; provers connect with `notary_unix_socket`). A stale socket
+/// file left at the path is replaced, and the file is removed again on `stop()`. The
+/// socket is served through the same gateway as per-tenant limits.
+///
+/// # Logging
+/// Server activity is forwarded to Python's `logging` under the `tlsnpy` logger, at
+/// `log_level` (`trace`, `debug`, `info`, `warn` or `error`). With `log_file`, it is
+/// written to that file instead, which is rotated once it would exceed
+/// `log_file_max_bytes`, keeping `log_file_backups` old files (`<log_file>.1` being the
+/// newest). Like the level, the destination is process-wide and follows the most
+/// recently constructed notary.
+///
+/// # Runtime
+/// As with `PyProver`, the server runs on a runtime of its own with `worker_threads`
+/// threads (2 by default; 1 selects a current-thread runtime) unless `runtime` is given.
+#[pyclass(unsendable)]
+pub struct PyNotary {
+    rt: runtime::SharedRuntime,
+    config: NotaryServerProperties,
+    /// Resolves with the server's error message if it stopped due to a failure.
+    server_handle: Option<JoinHandle<Result<(), String>>>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    /// Port the running server listens on, resolved from `port=0` at start.
+    port: Option<u16>,
+    /// Temporary files backing inline PEM material referenced by `config`.
+    pem_files: Vec<NamedTempFile>,
+    /// Admission policy enforced by a gateway in front of the server, if any.
+    gateway_policy: Option<Arc<gateway::Policy>>,
+    /// The servers behind the running gateway.
+    upstream: Option<Upstream>,
+    /// Socket the gateway listens on instead of `host:port`.
+    unix_socket_path: Option<String>,
+    /// Curve of the signing key, as named by `crypto_provider`.
+    signing_algorithm: &'static str,
+}
+
+#[pymethods]
+impl PyNotary {
+    #[new]
+    #[pyo3(signature = (
+        host,
+        port,
+        max_sent_data,
+        max_recv_data,
+        timeout_seconds,
+        tls_enabled,
+        tls_cert_path = None,
+        tls_key_path = None,
+        notary_key_path = None,
+        notary_pub_key_path = None,
+        *,
+        tls_cert_pem = None,
+        tls_key_pem = None,
+        notary_key_pem = None,
+        notary_pub_key_pem = None,
+        authorization_whitelist_csv = None,
+        log_level = "info",
+        runtime = None,
+        tenant_limits = None,
+        unix_socket_path = None,
+        log_file = None,
+        log_file_max_bytes = DEFAULT_LOG_FILE_MAX_BYTES,
+        log_file_backups = DEFAULT_LOG_FILE_BACKUPS,
+        worker_threads = None,
+        html_info = None,
+        max_concurrent_sessions = None,
+    ))]
+    fn new(
+        host: String,
+        port: u16,
+        max_sent_data: usize,
+        max_recv_data: usize,
+        timeout_seconds: u64,
+        tls_enabled: bool,
+        tls_cert_path: Option<String>,
+        tls_key_path: Option<String>,
+        notary_key_path: Option<String>,
+        notary_pub_key_path: Option<String>,
+        tls_cert_pem: Option<Vec<u8>>,
+        tls_key_pem: Option<Vec<u8>>,
+        notary_key_pem: Option<Vec<u8>>,
+        notary_pub_key_pem: Option<Vec<u8>>,
+        authorization_whitelist_csv: Option<String>,
+        log_level: &str,
+        runtime: Option<PyRef<'_, PyRuntime>>,
+        tenant_limits: Option<HashMap<String, (usize, usize)>>,
+        unix_socket_path: Option<String>,
+        log_file: Option<String>,
+        log_file_max_bytes: u64,
+        log_file_backups: u32,
+        worker_threads: Option<usize>,
+        html_info: Option<String>,
+        max_concurrent_sessions: Option<usize>,
+    ) -> PyResult<Self> {
+        if cfg!(not(unix)) && unix_socket_path.is_some() {
+            return Err(PyValueError::new_err(
+                "unix_socket_path is not supported on this platform",
+            ));
+        }
+
+        let bind_ip = bind_address(&host)?;
+        if max_concurrent_sessions == Some(0) {
+            return Err(PyValueError::new_err("max_concurrent_sessions must be greater than zero"));
+        }
+
+        let log_level = logging::parse_level(log_level)?;
+        logging::init(log_level)?;
+        logging::set_log_file(log_file.as_deref(), log_file_max_bytes, log_file_backups)?;
+
+        let mut pem_files = Vec::new();
+        let tls_cert_path = resolve_pem(tls_cert_path, tls_cert_pem, &mut pem_files)?;
+        let tls_key_path = resolve_pem(tls_key_path, tls_key_pem, &mut pem_files)?;
+        let notary_key_path = resolve_pem(notary_key_path, notary_key_pem, &mut pem_files)?
+            .ok_or_else(|| PyValueError::new_err("notary_key_path or notary_key_pem is required"))?;
+        let notary_pub_key_path = resolve_pem(notary_pub_key_path, notary_pub_key_pem, &mut pem_files)?
+            .ok_or_else(|| {
+                PyValueError::new_err("notary_pub_key_path or notary_pub_key_pem is required")
+            })?;
+        let notary_key =
+            std::fs::read(&notary_key_path).map_err(|e| io_error(e, &notary_key_path))?;
+        let notary_pub_key =
+            std::fs::read(&notary_pub_key_path).map_err(|e| io_error(e, &notary_pub_key_path))?;
+        let signing_algorithm = keys::check_keypair(&notary_key, &notary_pub_key)
+            .map_err(|e| PyValueError::new_err(format!("Invalid notary signing keys: {e}")))?;
+
+        let whitelist = authorization_whitelist_csv
+            .as_deref()
+            .map(whitelist::Whitelist::load)
+            .transpose()
+            .map_err(|e| PyValueError::new_err(format!("Invalid authorization whitelist: {e:#}")))?;
+
+        // The gateway always listens in the server's place, but only screens sessions to
+        // limit them or check the whitelist
+        let needs_gateway =
+            tenant_limits.is_some() || whitelist.is_some() || max_concurrent_sessions.is_some();
+        let mut limits = HashMap::new();
+        for (api_key, (max_sent_data, max_recv_data)) in tenant_limits.unwrap_or_default() {
+            if max_sent_data == 0 || max_recv_data == 0 {
+                return Err(PyValueError::new_err(format!(
+                    "Limits for tenant {api_key:?} must be greater than zero"
+                )));
+            }
+            limits.insert(api_key, gateway::Limits { max_sent_data, max_recv_data });
+        }
+        let gateway_policy = needs_gateway.then(|| {
+            Arc::new(gateway::Policy {
+                default_limits: gateway::Limits { max_sent_data, max_recv_data },
+                tenant_limits: limits,
+                whitelist,
+                max_sessions: max_concurrent_sessions,
+                sessions: Arc::new(Semaphore::new(gateway::Policy::session_permits(
+                    max_concurrent_sessions,
+                ))),
+            })
+        });
+
+        let config = NotaryServerProperties {
+            server: ServerProperties {
+                name: "PyNotary".to_string(),
+                host: bind_ip.to_string(),
+                port,
+                html_info: html_info.unwrap_or_default(),
+            },
+            notarization: NotarizationProperties {
+                max_sent_data,
+                max_recv_data,
+                timeout: timeout_seconds,
+            },
+            tls: TLSProperties {
+                enabled: tls_enabled,
+                private_key_pem_path: tls_key_path,
+                certificate_pem_path: tls_cert_path,
+            },
+            notary_key: NotarySigningKeyProperties {
+                private_key_pem_path: notary_key_path,
+                public_key_pem_path: notary_pub_key_path,
+            },
+            logging: LoggingProperties {
+                level: log_level.to_string(),
+                filter: None,
+                ..Default::default()
+            },
+            authorization: AuthorizationProperties {
+                enabled: authorization_whitelist_csv.is_some(),
+                whitelist_csv_path: authorization_whitelist_csv,
+            },
+        };
+
+        Ok(Self {
+            rt: runtime::shared_or_new(runtime, worker_threads)?,
+            config,
+            server_handle: None,
+            shutdown_tx: None,
+            port: None,
+            pem_files,
+            gateway_policy,
+            upstream: None,
+            unix_socket_path,
+            signing_algorithm,
+        })
+    }
+
+    /// Creates a notary that runs on the shared `runtime` instead of its own.
+    ///
+    /// Accepts the same arguments as the constructor.
+    #[classmethod]
+    #[pyo3(signature = (runtime, *args, **kwargs))]
+    fn with_runtime<'py>(
+        cls: &Bound<'py, PyType>,
+        runtime: Py<PyRuntime>,
+        args: &Bound<'py, PyTuple>,
+        kwargs: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        runtime::construct_with_runtime(cls, runtime, args, kwargs)
+    }
+
+    /// Creates a notary for local development, with a self-signed TLS certificate and
+    /// an ephemeral secp256k1 signing keypair generated in memory.
+    ///
+    /// DEVELOPMENT ONLY: the keys live as long as the notary and nothing vouches for the
+    /// certificate, so attestations from it prove nothing to anyone else. Point a prover
+    /// at it with `notary_tls=True` and `notary_root_cert_pem=notary.tls_cert_pem()`,
+    /// and verify against `notary.public_key_pem()`.
+    #[staticmethod]
+    #[pyo3(signature = (
+        host = "127.0.0.1".to_string(),
+        port = 0,
+        max_sent_data = DEFAULT_MAX_SENT_DATA,
+        max_recv_data = DEFAULT_MAX_RECV_DATA,
+        timeout_seconds = 60,
+        log_level = "info",
+    ))]
+    fn dev(
+        host: String,
+        port: u16,
+        max_sent_data: usize,
+        max_recv_data: usize,
+        timeout_seconds: u64,
+        log_level: &str,
+    ) -> PyResult<Self> {
+        let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+        if !names.contains(&host) {
+            names.push(host.clone());
+        }
+        let (cert_pem, tls_key_pem) = keys::self_signed_certificate(names)
+            .map_err(|e| TlsnError::new_err(format!("Certificate generation failed: {e}")))?;
+        let (notary_key_pem, notary_pub_key_pem) = keys::generate_keypair("secp256k1")
+            .map_err(|e| TlsnError::new_err(format!("Key generation failed: {e}")))?;
+
+        let notary = Self::new(
+            host,
+            port,
+            max_sent_data,
+            max_recv_data,
+            timeout_seconds,
+            true,
+            None,
+            None,
+            None,
+            None,
+            Some(cert_pem.into_bytes()),
+            Some(tls_key_pem.into_bytes()),
+            Some(notary_key_pem.into_bytes()),
+            Some(notary_pub_key_pem.into_bytes()),
+            None,
+            log_level,
+            None,
+            None,
+            None,
+            None,
+            DEFAULT_LOG_FILE_MAX_BYTES,
+            DEFAULT_LOG_FILE_BACKUPS,
+            None,
+            None,
+            None,
+        )?;
+        tracing::warn!("Development notary with generated keys; do not use in production");
+        Ok(notary)
+    }
+
+    /// Starts the server and waits until it accepts connections.
+    ///
+    /// Raises if the server fails to come up, e.g. because the port is in use.
+    fn start(&mut self) -> PyResult<()> {
+        if self.is_running() {
+            return Err(TlsnError::new_err("Notary server is already running"));
+        }
+
+        // Clone config for the server task
+        let mut config = self.config.clone();
+
+        // `run_server` binds its address itself and neither reports the port nor why
+        // binding failed, and checking the address beforehand would release it for others
+        // to take. So the gateway, admitting everything if it has no policy, holds it.
+        let policy = self.gateway_policy.clone().unwrap_or_else(|| {
+            Arc::new(gateway::Policy::open(gateway::Limits {
+                max_sent_data: config.notarization.max_sent_data,
+                max_recv_data: config.notarization.max_recv_data,
+            }))
+        });
+        let gateway = self.bind_gateway(&mut config, policy.clone())?;
+        let routes = self.upstream.as_ref().expect("bind_gateway sets the upstream").routes.clone();
+        let probe_host = connectable_host(&config.server.host);
+        let probe_port = config.server.port;
+
+        // Create a new shutdown channel
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        self.shutdown_tx = Some(shutdown_tx);
+
+        // Spawn the server task
+        let handle = self.rt.spawn(async move {
+            // Create a future that completes when shutdown signal is received
+            let shutdown = async {
+                let _ = shutdown_rx.await;
+            };
+
+            // Run the server in a separate task so we can select between it and shutdown.
+            // The guard also aborts it if this task is aborted by a forced stop.
+            let server_config = config.clone();
+            let mut server = AbortOnDrop(tokio::spawn(serve_until_retired(server_config, routes)));
+            let mut gateway = AbortOnDrop(tokio::spawn(gateway));
+            let gateway_exit = async {
+                match (&mut gateway.0).await {
+                    Ok(Ok(())) => "gateway stopped".to_string(),
+                    Ok(Err(e)) => format!("{e:#}"),
+                    Err(e) => e.to_string(),
+                }
+            };
+
+            // Wait for either server completion or shutdown signal
+            tokio::select! {
+                _ = shutdown => {
+                    // Let notarizations in progress finish before the server is dropped;
+                    // the gateway holds a permit for each until its relay ends
+                    tracing::info!("Notary server shutting down...");
+                    let idle = tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, policy.idle()).await;
+                    if idle.is_err() {
+                        tracing::warn!(
+                            "Notary server stopped with {} notarizations in progress",
+                            policy.active_sessions()
+                        );
+                    }
+                    Ok(())
+                }
+                result = &mut server.0 => {
+                    match result {
+                        Ok(Ok(())) => {
+                            tracing::info!("Notary server stopped normally");
+                            Ok(())
+                        }
+                        Ok(Err(e)) => {
+                            tracing::error!("Notary server error: {e}");
+                            Err(e)
+                        }
+                        Err(e) => {
+                            tracing::error!("Notary server task error: {e}");
+                            Err(e.to_string())
+                        }
+                    }
+                }
+                e = gateway_exit => {
+                    tracing::error!("Notary gateway error: {e}");
+                    Err(format!("Notary gateway error: {e}"))
+                }
+            }
+        });
+
+        let bound = wait_until_bound(&self.rt, &handle, &probe_host, probe_port);
+        self.server_handle = Some(handle);
+
+        if let Err(err) = bound {
+            // Dropping the sender shuts the server task down if it is still running
+            self.shutdown_tx = None;
+            self.port = None;
+            self.upstream = None;
+            if let Some(handle) = self.server_handle.take() {
+                if let Ok(Err(e)) = self.rt.block_on(handle) {
+                    return Err(TlsnError::new_err(format!("Notary server failed to start: {e}")));
+                }
+            }
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the HTML the notary serves at its root, e.g. a landing or status page.
+    ///
+    /// Like the `html_info` constructor argument, this takes effect the next time the
+    /// server starts; restart a running notary to serve the new page.
+    fn set_html_info(&mut self, html_info: String) {
+        self.config.server.html_info = html_info;
+    }
+
+    /// Returns how many notarizations are in progress.
+    ///
+    /// Only notaries served through the gateway count them (see "Concurrent sessions"),
+    /// so this raises for others.
+    fn active_sessions(&self) -> PyResult<usize> {
+        self.gateway_policy
+            .as_ref()
+            .map(|policy| policy.active_sessions())
+            .ok_or_else(|| {
+                TlsnError::new_err(
+                    "Notary does not count sessions; pass max_concurrent_sessions to enable it",
+                )
+            })
+    }
+
+    /// Returns whether the server task is still alive.
+    fn is_running(&self) -> bool {
+        self.server_handle.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Stops the server, waiting at most `timeout_seconds` for it to shut down.
+    ///
+    /// Notarizations in progress get up to 30 seconds to finish first; sessions not yet
+    /// notarizing are dropped. If the timeout expires the server task is aborted and a
+    /// `TimeoutError` is raised to signal the forced shutdown. Without a timeout this
+    /// waits for the notarizations, or the 30 seconds, to end.
+    #[pyo3(signature = (timeout_seconds = None))]
+    fn stop(&mut self, timeout_seconds: Option<u64>) -> PyResult<()> {
+        // Send shutdown signal if we have a sender
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+        self.port = None;
+        // Servers started by key rotation are aborted once the main one has stopped
+        let _upstream = self.upstream.take();
+        if let Some(path) = &self.unix_socket_path {
+            if self.server_handle.is_some() {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+
+        let Some(mut handle) = self.server_handle.take() else {
+            return Ok(());
+        };
+
+        // Wait for the server task to complete
+        let Some(timeout_seconds) = timeout_seconds else {
+            self.rt.block_on(async {
+                let _ = handle.await;
+            });
+            return Ok(());
+        };
+
+        let stopped = self.rt.block_on(tokio::time::timeout(
+            Duration::from_secs(timeout_seconds),
+            &mut handle,
+        ));
+        if stopped.is_err() {
+            handle.abort();
+            return Err(TimeoutError::new_err(format!(
+                "Notary server did not stop within {timeout_seconds}s and was forcibly shut down"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the port the server listens on.
+    ///
+    /// This is the OS-assigned port when the notary was created with `port=0`.
+    fn bound_port(&self) -> PyResult<u16> {
+        if self.unix_socket_path.is_some() {
+            return Err(TlsnError::new_err("Notary server listens on a Unix socket"));
+        }
+        self.port
+            .ok_or_else(|| TlsnError::new_err("Notary server has not been started"))
+    }
+
+    /// Re-reads the authorization whitelist CSV, returning the number of API keys now
+    /// admitted. The current keys stay in effect if the file can't be read.
+    fn reload_whitelist(&self) -> PyResult<usize> {
+        self.whitelist()?
+            .reload()
+            .map_err(|e| TlsnError::new_err(format!("Reloading the whitelist failed: {e:#}")))
+    }
+
+    /// Admits `token`, adding it to the whitelist CSV under `name`. Returns False if it
+    /// was already listed.
+    #[pyo3(signature = (token, name = "tlsnpy"))]
+    fn add_token(&self, token: &str, name: &str) -> PyResult<bool> {
+        if token.trim().is_empty() || token.trim() != token {
+            return Err(PyValueError::new_err(
+                "token must be non-empty without surrounding whitespace",
+            ));
+        }
+        self.whitelist()?
+            .add(token, name)
+            .map_err(|e| TlsnError::new_err(format!("Updating the whitelist failed: {e:#}")))
+    }
+
+    /// Stops admitting `token` and removes it from the whitelist CSV. Returns False if it
+    /// wasn't listed.
+    fn remove_token(&self, token: &str) -> PyResult<bool> {
+        self.whitelist()?
+            .remove(token)
+            .map_err(|e| TlsnError::new_err(format!("Updating the whitelist failed: {e:#}")))
+    }
+
+    /// Returns the curve the notary signs attestations with: `"secp256k1"` or `"p256"`.
+    ///
+    /// Provers of this notary must pass the same value as `crypto_provider`.
+    fn signing_algorithm(&self) -> &'static str {
+        self.signing_algorithm
+    }
+
+    /// Replaces the signing keypair with `new_private_pem`/`new_public_pem`, in the
+    /// formats of `notary_key_pem`/`notary_pub_key_pem`.
+    ///
+    /// See "Key rotation". The new keys may be on another curve, which provers must then
+    /// pass as `crypto_provider`.
+    fn rotate_signing_key(
+        &mut self,
+        new_private_pem: Vec<u8>,
+        new_public_pem: Vec<u8>,
+    ) -> PyResult<()> {
+        let signing_algorithm = keys::check_keypair(&new_private_pem, &new_public_pem)
+            .map_err(|e| PyValueError::new_err(format!("Invalid notary signing keys: {e}")))?;
+        let running = self.is_running();
+
+        let mut pem_files = Vec::new();
+        let notary_key = NotarySigningKeyProperties {
+            private_key_pem_path: store_pem(&new_private_pem, &mut pem_files)?,
+            public_key_pem_path: store_pem(&new_public_pem, &mut pem_files)?,
+        };
+
+        if let Some(upstream) = self.upstream.as_mut().filter(|_| running) {
+            let mut config = upstream.config.clone();
+            config.notary_key = notary_key.clone();
+            config.server.port = bind_port(&config.server.host, 0)?;
+
+            let server_config = config.clone();
+            let routes = upstream.routes.clone();
+            let mut server = AbortOnDrop(self.rt.spawn(serve_until_retired(server_config, routes)));
+            if let Err(err) =
+                wait_until_bound(&self.rt, &server.0, &config.server.host, config.server.port)
+            {
+                if server.0.is_finished() {
+                    if let Ok(Err(e)) = self.rt.block_on(&mut server.0) {
+                        return Err(TlsnError::new_err(format!(
+                            "Notary server with the new signing key failed to start: {e}"
+                        )));
+                    }
+                }
+                return Err(err);
+            }
+
+            upstream.routes.switch((Ipv4Addr::LOCALHOST, config.server.port).into());
+            upstream.config = config;
+            upstream.servers.push(server);
+            tracing::info!("Notary signing key rotated, new sessions use the new key");
+        }
+
+        self.config.notary_key = notary_key;
+        self.signing_algorithm = signing_algorithm;
+        self.pem_files.extend(pem_files);
+        Ok(())
+    }
+
+    /// Returns the configuration the server is started with, as nested dicts mirroring
+    /// the notary server's: `server`, `notarization`, `tls`, `notary_key`, `logging` and
+    /// `authorization`.
+    ///
+    /// Paths are the resolved ones, so keys given as inline PEM show their temporary
+    /// files. When the notary runs behind the gateway, this is its public side; the
+    /// server behind it runs on loopback without TLS or authorization.
+    fn config<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let config = &self.config;
+
+        let server = PyDict::new(py);
+        server.set_item("name", &config.server.name)?;
+        server.set_item("host", &config.server.host)?;
+        server.set_item("port", config.server.port)?;
+        server.set_item("html_info", &config.server.html_info)?;
+
+        let notarization = PyDict::new(py);
+        notarization.set_item("max_sent_data", config.notarization.max_sent_data)?;
+        notarization.set_item("max_recv_data", config.notarization.max_recv_data)?;
+        notarization.set_item("timeout", config.notarization.timeout)?;
+
+        let tls = PyDict::new(py);
+        tls.set_item("enabled", config.tls.enabled)?;
+        tls.set_item("private_key_pem_path", &config.tls.private_key_pem_path)?;
+        tls.set_item("certificate_pem_path", &config.tls.certificate_pem_path)?;
+
+        let notary_key = PyDict::new(py);
+        notary_key.set_item("private_key_pem_path", &config.notary_key.private_key_pem_path)?;
+        notary_key.set_item("public_key_pem_path", &config.notary_key.public_key_pem_path)?;
+
+        let logging = PyDict::new(py);
+        logging.set_item("level", &config.logging.level)?;
+
+        let authorization = PyDict::new(py);
+        authorization.set_item("enabled", config.authorization.enabled)?;
+        authorization.set_item("whitelist_csv_path", &config.authorization.whitelist_csv_path)?;
+
+        let dict = PyDict::new(py);
+        dict.set_item("server", server)?;
+        dict.set_item("notarization", notarization)?;
+        dict.set_item("tls", tls)?;
+        dict.set_item("notary_key", notary_key)?;
+        dict.set_item("logging", logging)?;
+        dict.set_item("authorization", authorization)?;
+        Ok(dict)
+    }
+
+    /// Returns the configured notary public key as PEM.
+    fn public_key_pem(&self) -> PyResult<Vec<u8>> {
+        let path = &self.config.notary_key.public_key_pem_path;
+        std::fs::read(path).map_err(|e| io_error(e, path))
+    }
+
+    /// Returns the notary's TLS certificate as PEM, e.g. to trust a `dev()` notary.
+    fn tls_cert_pem(&self) -> PyResult<Vec<u8>> {
+        let path = self.config.tls.certificate_pem_path.as_ref()
+            .ok_or_else(|| TlsnError::new_err("Notary has no TLS certificate"))?;
+        std::fs::read(path).map_err(|e| io_error(e, path))
+    }
+
+    /// Generates a fresh notary signing keypair as `(private_pem, public_pem)`.
+    ///
+    /// The private key is PKCS#8 and the public key SubjectPublicKeyInfo, the formats
+    /// expected for `notary_key_pem`/`notary_pub_key_pem`. `curve` is `"secp256k1"`
+    /// (the notary default) or `"p256"`.
+    #[staticmethod]
+    #[pyo3(signature = (curve = "secp256k1"))]
+    fn generate_keypair(curve: &str) -> PyResult<(Vec<u8>, Vec<u8>)> {
+        let (private_pem, public_pem) = keys::generate_keypair(curve)
+            .map_err(|e| PyValueError::new_err(format!("Key generation failed: {e}")))?;
+        Ok((private_pem.into_bytes(), public_pem.into_bytes()))
+    }
+
+    /// Starts the server for the duration of a `with` block.
+    ///
+    /// If `start()` raises, the block is not entered and nothing is left running.
+    fn __enter__(mut slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        slf.start()?;
+        Ok(slf)
+    }
+
+    /// Stops the server when leaving the `with` block; exceptions are not suppressed.
+    fn __exit__(
+        &mut self,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        self.stop(None)
+    }
+}
+
+impl PyNotary {
+    /// Binds the gateway's public listener and moves the server in `config` to a
+    /// private loopback port behind it, returning the gateway to run.
+    fn bind_gateway(
+        &mut self,
+        config: &mut NotaryServerProperties,
+        policy: Arc<gateway::Policy>,
+    ) -> PyResult<impl Future<Output = anyhow::Result<()>> + Send + 'static> {
+        let start_failed = |e: &dyn std::fmt::Display| {
+            TlsnError::new_err(format!("Notary server failed to start: {e}"))
+        };
+
+        let listener = match &self.unix_socket_path {
+            #[cfg(unix)]
+            Some(path) => {
+                remove_stale_socket(path).map_err(|e| start_failed(&e))?;
+                let _guard = self.rt.enter();
+                let listener = tokio::net::UnixListener::bind(path)
+                    .map_err(|e| io_error(e, path))?;
+                self.port = None;
+                gateway::Listener::Unix(listener)
+            }
+            _ => {
+                let public_addr = (config.server.host.clone(), config.server.port);
+                let listener = self.rt
+                    .block_on(tokio::net::TcpListener::bind(public_addr))
+                    .map_err(|e| bind_failed(&config.server.host, config.server.port, &e))?;
+                let public_port = listener.local_addr().map_err(|e| start_failed(&e))?.port();
+                self.port = Some(public_port);
+                gateway::Listener::Tcp(listener)
+            }
+        };
+
+        let tls = if config.tls.enabled {
+            let (Some(cert_path), Some(key_path)) =
+                (&config.tls.certificate_pem_path, &config.tls.private_key_pem_path)
+            else {
+                return Err(PyValueError::new_err("TLS requires a certificate and a private key"));
+            };
+            Some(gateway::tls_acceptor(cert_path, key_path).map_err(|e| start_failed(&e))?)
+        } else {
+            None
+        };
+
+        // The gateway terminates TLS and admits at most each tenant's ceiling, so the
+        // server behind it runs in plain TCP with limits wide enough for every tenant.
+        let upstream_limits = policy.upstream_limits();
+        config.server.host = Ipv4Addr::LOCALHOST.to_string();
+        config.server.port = bind_port(&config.server.host, 0)?;
+        config.tls.enabled = false;
+        // The gateway checks API keys against its live whitelist instead
+        config.authorization.enabled = false;
+        config.notarization.max_sent_data = upstream_limits.max_sent_data;
+        config.notarization.max_recv_data = upstream_limits.max_recv_data;
+
+        let upstream = (Ipv4Addr::LOCALHOST, config.server.port).into();
+        let routes = Arc::new(gateway::Routes::new(upstream));
+        self.upstream = Some(Upstream {
+            routes: routes.clone(),
+            config: config.clone(),
+            servers: Vec::new(),
+        });
+        Ok(gateway::serve(listener, routes, tls, policy))
+    }
+
+    fn whitelist(&self) -> PyResult<&whitelist::Whitelist> {
+        self.gateway_policy
+            .as_ref()
+            .and_then(|policy| policy.whitelist.as_ref())
+            .ok_or_else(|| TlsnError::new_err("Authorization is not enabled for this notary"))
+    }
+}
+
+/// The notary servers behind a running gateway.
+struct Upstream {
+    routes: Arc<gateway::Routes>,
+    /// Configuration of the server new sessions go to.
+    config: NotaryServerProperties,
+    /// Servers started by key rotation.
+    servers: Vec<AbortOnDrop<Result<(), String>>>,
+}
+
+/// Runs the notary server configured by `config` until `routes` has retired it.
+///
+/// A retired server is shut down, but the future then stays pending so that its task
+/// only ever finishes if the server fails.
+async fn serve_until_retired(
+    config: NotaryServerProperties,
+    routes: Arc<gateway::Routes>,
+) -> Result<(), String> {
+    let port = config.server.port;
+    tokio::select! {
+        result = run_server(&config) => result.map_err(|e| e.to_string()),
+        () = routes.retired((Ipv4Addr::LOCALHOST, port).into()) => {
+            tracing::info!("Notary server on port {port} replaced by key rotation shut down");
+            std::future::pending().await
+        }
+    }
+}
+
+/// Blocks until the server accepts connections on `host:port` or its task exits.
+fn wait_until_bound<T>(
+    rt: &Runtime,
+    handle: &JoinHandle<T>,
+    host: &str,
+    port: u16,
+) -> PyResult<()> {
+    let addr = (host, port);
+    rt.block_on(async {
+        let wait = async {
+            loop {
+                if handle.is_finished() {
+                    return Err(TlsnError::new_err("Notary server exited before binding"));
+                }
+                if TcpStream::connect(&addr).await.is_ok() {
+                    return Ok(());
+                }
+                tokio::time::sleep(BIND_POLL_INTERVAL).await;
+            }
+        };
+        tokio::time::timeout(BIND_TIMEOUT, wait)
+            .await
+            .map_err(|_| TimeoutError::new_err("Timed out waiting for notary server to bind"))?
+    })
+}
+
+/// Default size at which the notary's log file is rotated.
+const DEFAULT_LOG_FILE_MAX_BYTES: u64 = 10 * 1024 * 1024;
+/// Default number of rotated notary log files kept.
+const DEFAULT_LOG_FILE_BACKUPS: u32 = 3;
+
+/// How long notarizations in progress get to finish once shutdown is requested.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Aborts the wrapped task when dropped.
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// How long to wait for the notary server to start accepting connections.
+const BIND_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to probe whether the notary server is accepting connections.
+const BIND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Resolves a PEM input to a file path, writing inline PEM to a private temporary file.
+///
+/// Inline PEM takes precedence over `path`.
+fn resolve_pem(
+    path: Option<String>,
+    pem: Option<Vec<u8>>,
+    pem_files: &mut Vec<NamedTempFile>,
+) -> PyResult<Option<String>> {
+    match pem {
+        Some(pem) => store_pem(&pem, pem_files).map(Some),
+        None => Ok(path),
+    }
+}
+
+/// Writes inline PEM to a private temporary file kept in `pem_files`, returning its path.
+fn store_pem(pem: &[u8], pem_files: &mut Vec<NamedTempFile>) -> PyResult<String> {
+    let write = || {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(pem)?;
+        file.flush()?;
+        Ok::<_, std::io::Error>(file)
+    };
+    let file = write().map_err(|e| TlsnError::new_err(format!("Failed to store PEM: {e}")))?;
+
+    let path = file.path().to_string_lossy().into_owned();
+    pem_files.push(file);
+    Ok(path)
+}
+
+/// Removes a socket file left behind by a previous server at `path`, refusing to touch
+/// anything that isn't a socket.
+#[cfg(unix)]
+fn remove_stale_socket(path: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        Ok(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{path} exists and is not a socket"),
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Resolves the notary's `host` to the address it binds: an IPv4 or IPv6 address
+/// (with or without brackets), or a host name resolving to one.
+fn bind_address(host: &str) -> PyResult<IpAddr> {
+    let bare = host.trim().trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Ok(ip);
+    }
+    let resolved = (bare, 0).to_socket_addrs().map_err(|e| {
+        PyValueError::new_err(format!("Invalid host {host:?}: not an IP address, {e}"))
+    })?;
+    let mut addrs: Vec<SocketAddr> = resolved.collect();
+    // Stable, so the resolver order is kept within each family
+    addrs.sort_by_key(|addr| !addr.is_ipv4());
+    addrs.first().map(SocketAddr::ip).ok_or_else(|| {
+        PyValueError::new_err(format!("Invalid host {host:?}: resolves to no address"))
+    })
+}
+
+/// Binds `host:port` and releases it, returning the port bound; for port 0, a free
+/// one picked by the OS.
+///
+/// Another process may take the port before the notary server binds it again, so this
+/// only picks loopback ports for servers behind the gateway, which `run_server` has to
+/// bind by itself.
+fn bind_port(host: &str, port: u16) -> PyResult<u16> {
+    let listener =
+        std::net::TcpListener::bind((host, port)).map_err(|e| bind_failed(host, port, &e))?;
+    let port = listener.local_addr().map_err(|e| bind_failed(host, port, &e))?.port();
+    Ok(port)
+}
+
+fn bind_failed(host: &str, port: u16, e: &std::io::Error) -> PyErr {
+    let addr = match host.parse::<IpAddr>() {
+        Ok(ip) => SocketAddr::new(ip, port).to_string(),
+        Err(_) => format!("{host}:{port}"),
+    };
+    let hint = match e.kind() {
+        std::io::ErrorKind::AddrInUse => " (is another server listening there?)",
+        std::io::ErrorKind::AddrNotAvailable => " (no local interface has this address)",
+        std::io::ErrorKind::PermissionDenied => " (ports below 1024 need privileges)",
+        _ => "",
+    };
+    TlsnError::new_err(format!("Notary server failed to bind {addr}: {e}{hint}"))
+}
+
+/// Maps wildcard bind addresses to the loopback address of the same family.
+fn connectable_host(host: &str) -> String {
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) if ip.is_unspecified() => Ipv4Addr::LOCALHOST.to_string(),
+        Ok(IpAddr::V6(ip)) if ip.is_unspecified() => Ipv6Addr::LOCALHOST.to_string(),
+        _ => host.to_string(),
+    }
+}
@@ -0,0 +1,211 @@
+use std::ops::Range;
+
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+
+use regex::bytes::Regex;
+
+use tlsn_core::attestation::Attestation;
+use tlsn_core::presentation::Presentation;
+use tlsn_core::{CryptoProvider, Secrets};
+
+use crate::errors::TlsnError;
+use crate::{attestation, http};
+
+/// Builds a selective-disclosure presentation from an attestation and its secrets.
+///
+/// Only the byte ranges passed to `reveal_sent`/`reveal_recv` are disclosed to the
+/// verifier; the rest of the transcript stays committed but hidden.
+///
+/// Bytes matching a pattern given to `redact_sent_regex`/`redact_recv_regex` are never
+/// revealed. If a direction has redaction patterns but no explicit reveal ranges, the
+/// whole transcript of that direction is revealed except for the matches.
+///
+/// `reveal_headers_only`/`reveal_body_only` compute the ranges from the HTTP framing of
+/// the transcript, e.g. to prove which URL a response came from while hiding its body.
+#[pyclass]
+pub struct PyPresentationBuilder {
+    attestation: Attestation,
+    secrets: Secrets,
+    reveal_sent: Vec<Range<usize>>,
+    reveal_recv: Vec<Range<usize>>,
+    redact_sent: Vec<Regex>,
+    redact_recv: Vec<Regex>,
+}
+
+#[pymethods]
+impl PyPresentationBuilder {
+    /// `passphrase` decrypts secrets encrypted with `encrypt_secrets` or
+    /// `finalize_notarize(secrets_passphrase=...)`.
+    #[new]
+    #[pyo3(signature = (attestation_bytes, secrets_bytes, passphrase = None))]
+    fn new(
+        attestation_bytes: Vec<u8>,
+        secrets_bytes: Vec<u8>,
+        passphrase: Option<&str>,
+    ) -> PyResult<Self> {
+        let attestation = attestation::decode(&attestation_bytes)?;
+        let secrets = attestation::decode_secrets(&secrets_bytes, passphrase)?;
+
+        Ok(Self {
+            attestation,
+            secrets,
+            reveal_sent: Vec::new(),
+            reveal_recv: Vec::new(),
+            redact_sent: Vec::new(),
+            redact_recv: Vec::new(),
+        })
+    }
+
+    /// Reveals the given `(start, end)` byte ranges of the sent data.
+    fn reveal_sent(&mut self, ranges: Vec<(usize, usize)>) -> PyResult<()> {
+        self.reveal_sent.extend(to_ranges(ranges)?);
+        Ok(())
+    }
+
+    /// Reveals the given `(start, end)` byte ranges of the received data.
+    fn reveal_recv(&mut self, ranges: Vec<(usize, usize)>) -> PyResult<()> {
+        self.reveal_recv.extend(to_ranges(ranges)?);
+        Ok(())
+    }
+
+    /// Reveals the head (request or status line and headers) of every HTTP message in
+    /// the chosen directions, keeping the bodies hidden.
+    #[pyo3(signature = (sent = true, recv = true))]
+    fn reveal_headers_only(&mut self, sent: bool, recv: bool) -> PyResult<()> {
+        self.reveal_http_parts(sent, recv, |(head, _)| head)
+    }
+
+    /// Reveals the body of every HTTP message in the chosen directions, keeping the
+    /// heads hidden. Chunked bodies are revealed as sent, chunk framing included.
+    #[pyo3(signature = (sent = true, recv = true))]
+    fn reveal_body_only(&mut self, sent: bool, recv: bool) -> PyResult<()> {
+        self.reveal_http_parts(sent, recv, |(_, body)| body)
+    }
+
+    /// Hides every match of `pattern` in the sent data.
+    ///
+    /// The pattern is matched against the raw transcript bytes, so it also works on
+    /// non-UTF-8 data.
+    fn redact_sent_regex(&mut self, pattern: &str) -> PyResult<()> {
+        self.redact_sent.push(compile(pattern)?);
+        Ok(())
+    }
+
+    /// Hides every match of `pattern` in the received data.
+    ///
+    /// The pattern is matched against the raw transcript bytes, so it also works on
+    /// non-UTF-8 data.
+    fn redact_recv_regex(&mut self, pattern: &str) -> PyResult<()> {
+        self.redact_recv.push(compile(pattern)?);
+        Ok(())
+    }
+
+    /// Builds the presentation and returns it bincode-serialized.
+    fn build(&self) -> PyResult<Vec<u8>> {
+        let result = (|| {
+            let transcript = self.secrets.transcript();
+            let reveal_sent =
+                apply_redactions(&self.reveal_sent, &self.redact_sent, transcript.sent());
+            let reveal_recv =
+                apply_redactions(&self.reveal_recv, &self.redact_recv, transcript.received());
+
+            let mut builder = self.secrets.transcript_proof_builder();
+            for range in &reveal_sent {
+                builder.reveal_sent(range)?;
+            }
+            for range in &reveal_recv {
+                builder.reveal_recv(range)?;
+            }
+            let transcript_proof = builder.build()?;
+
+            let provider = CryptoProvider::default();
+            let mut builder = self.attestation.presentation_builder(&provider);
+            builder
+                .identity_proof(self.secrets.identity_proof())
+                .transcript_proof(transcript_proof);
+            let presentation: Presentation = builder.build()?;
+
+            Ok::<_, anyhow::Error>(attestation::Format::Bincode.encode(&presentation)?)
+        })();
+
+        result.map_err(|e| TlsnError::new_err(format!("Building presentation failed: {e}")))
+    }
+}
+
+impl PyPresentationBuilder {
+    fn reveal_http_parts(
+        &mut self,
+        sent: bool,
+        recv: bool,
+        part: fn((Range<usize>, Range<usize>)) -> Range<usize>,
+    ) -> PyResult<()> {
+        let transcript = self.secrets.transcript();
+        for (enabled, data, reveal) in [
+            (sent, transcript.sent(), &mut self.reveal_sent),
+            (recv, transcript.received(), &mut self.reveal_recv),
+        ] {
+            if !enabled {
+                continue;
+            }
+            let messages = http::message_ranges(data)
+                .map_err(|e| PyValueError::new_err(format!("Transcript is not HTTP: {e}")))?;
+            reveal.extend(messages.into_iter().map(part).filter(|range| !range.is_empty()));
+        }
+        Ok(())
+    }
+}
+
+fn to_ranges(ranges: Vec<(usize, usize)>) -> PyResult<Vec<Range<usize>>> {
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start >= end {
+                return Err(PyValueError::new_err(format!("Invalid range ({start}, {end})")));
+            }
+            Ok(start..end)
+        })
+        .collect()
+}
+
+fn compile(pattern: &str) -> PyResult<Regex> {
+    Regex::new(pattern).map_err(|e| PyValueError::new_err(format!("Invalid regex: {e}")))
+}
+
+/// Removes the bytes matched by `redactions` from the ranges to reveal.
+fn apply_redactions(
+    reveal: &[Range<usize>],
+    redactions: &[Regex],
+    data: &[u8],
+) -> Vec<Range<usize>> {
+    if redactions.is_empty() {
+        return reveal.to_vec();
+    }
+
+    let all = [0..data.len()];
+    let reveal = if reveal.is_empty() { &all[..] } else { reveal };
+    let mut matches: Vec<Range<usize>> = redactions
+        .iter()
+        .flat_map(|regex| regex.find_iter(data).map(|m| m.range()))
+        .filter(|range| !range.is_empty())
+        .collect();
+    matches.sort_by_key(|range| range.start);
+
+    let mut ranges = Vec::new();
+    for range in reveal {
+        let mut start = range.start;
+        for hole in &matches {
+            if hole.end <= start || hole.start >= range.end {
+                continue;
+            }
+            if hole.start > start {
+                ranges.push(start..hole.start);
+            }
+            start = start.max(hole.end);
+        }
+        if start < range.end {
+            ranges.push(start..range.end);
+        }
+    }
+    ranges
+}
@@ -0,0 +1,28 @@
+//! Progress reporting to an optional Python callback.
+
+use pyo3::prelude::*;
+
+/// Reports phase boundaries to a `progress(phase: str, fraction: float)` callable.
+///
+/// The callback runs on whichever thread drives the operation, acquiring the GIL for
+/// the call. Exceptions it raises are logged and otherwise ignored, so a faulty
+/// callback can't abort a notarization.
+#[derive(Debug, Default)]
+pub(crate) struct Progress(Option<Py<PyAny>>);
+
+impl Progress {
+    pub(crate) fn new(callback: Option<Py<PyAny>>) -> Self {
+        Self(callback)
+    }
+
+    pub(crate) fn report(&self, phase: &str, fraction: f64) {
+        let Some(callback) = &self.0 else {
+            return;
+        };
+        Python::with_gil(|py| {
+            if let Err(e) = callback.call1(py, (phase, fraction)) {
+                tracing::warn!("Progress callback failed in phase {phase:?}: {e}");
+            }
+        });
+    }
+}
@@ -0,0 +1,115 @@
+//! Running several notarizations in a row on one prover.
+
+use pyo3::exceptions::{PyInterruptedError, PyKeyboardInterrupt, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use super::options::{ConnectOptions, NotarizeOptions};
+use super::{lock_state, ProverState, PyProver};
+
+/// One notarization of a `notarize_many` batch.
+pub(crate) struct Spec {
+    server_host: String,
+    server_port: u16,
+    raw_http: Vec<u8>,
+}
+
+/// The outcome of one notarization: `(attestation, secrets)` or why it failed.
+pub(crate) type Outcome = PyResult<(Vec<u8>, Vec<u8>)>;
+
+/// Reads the `{server_host, server_port, raw_http}` dicts passed to `notarize_many`.
+pub(crate) fn parse_specs(requests: &[Bound<'_, PyDict>]) -> PyResult<Vec<Spec>> {
+    requests
+        .iter()
+        .enumerate()
+        .map(|(index, request)| {
+            let field = |name: &str| {
+                request.get_item(name)?.ok_or_else(|| {
+                    PyValueError::new_err(format!("Request {index} is missing {name:?}"))
+                })
+            };
+            Ok(Spec {
+                server_host: field("server_host")?.extract()?,
+                server_port: field("server_port")?.extract()?,
+                raw_http: field("raw_http")?.extract()?,
+            })
+        })
+        .collect()
+}
+
+/// Runs each spec in turn, continuing past failures.
+///
+/// Only cancellation and interrupts stop the batch, since every later notarization would
+/// be cancelled too.
+pub(crate) fn run(
+    prover: &mut PyProver,
+    specs: Vec<Spec>,
+    verify_with_pub_key: Option<Vec<u8>>,
+    format: &str,
+) -> PyResult<Vec<Outcome>> {
+    let mut outcomes = Vec::with_capacity(specs.len());
+    for (index, spec) in specs.into_iter().enumerate() {
+        match notarize(prover, spec, verify_with_pub_key.clone(), format) {
+            Err(e) if Python::with_gil(|py| is_interrupt(py, &e)) => return Err(e),
+            outcome => {
+                if let Err(e) = &outcome {
+                    tracing::warn!("Notarization {index} of the batch failed: {e}");
+                }
+                outcomes.push(outcome);
+            }
+        }
+    }
+    Ok(outcomes)
+}
+
+fn is_interrupt(py: Python<'_>, e: &PyErr) -> bool {
+    e.is_instance_of::<PyInterruptedError>(py) || e.is_instance_of::<PyKeyboardInterrupt>(py)
+}
+
+fn notarize(
+    prover: &mut PyProver,
+    spec: Spec,
+    verify_with_pub_key: Option<Vec<u8>>,
+    format: &str,
+) -> Outcome {
+    // A successful finalize leaves the next session set up; a failure leaves none
+    let ready = matches!(&*lock_state(&prover.inner), Some(ProverState::Setup(_)));
+    if !ready {
+        prover.new_session()?;
+    }
+
+    prover.connect_with(spec.server_host, spec.server_port, ConnectOptions::default())?;
+    prover.send_request(spec.raw_http, false, false)?;
+    prover.start_notarize()?;
+    prover.finalize_with(NotarizeOptions { verify_with_pub_key, format, ..Default::default() })
+}
+
+/// Converts outcomes to the dicts returned to Python: `attestation` and `secrets` for
+/// successes, `error` (the message) and `error_type` (the exception class) for failures,
+/// with the fields of the other case set to `None`.
+pub(crate) fn to_dicts(
+    py: Python<'_>,
+    outcomes: Vec<Outcome>,
+) -> PyResult<Vec<Bound<'_, PyDict>>> {
+    outcomes
+        .into_iter()
+        .map(|outcome| {
+            let item = PyDict::new(py);
+            match outcome {
+                Ok((attestation, secrets)) => {
+                    item.set_item("attestation", attestation)?;
+                    item.set_item("secrets", secrets)?;
+                    item.set_item("error", py.None())?;
+                    item.set_item("error_type", py.None())?;
+                }
+                Err(e) => {
+                    item.set_item("attestation", py.None())?;
+                    item.set_item("secrets", py.None())?;
+                    item.set_item("error", e.value(py).to_string())?;
+                    item.set_item("error_type", e.get_type(py).name()?)?;
+                }
+            }
+            Ok(item)
+        })
+        .collect()
+}
@@ -0,0 +1,126 @@
+//! Arguments of `PyProver`'s constructor, `connect()` and `finalize_notarize()` by name,
+//! for calling them from Rust with the Python defaults for anything not given.
+
+use pyo3::prelude::*;
+
+use super::{DEFAULT_MAX_RECV_DATA, DEFAULT_MAX_SENT_DATA};
+use crate::runtime::PyRuntime;
+
+/// The arguments of `PyProver(...)`; see the constructor for each.
+pub(crate) struct ProverOptions<'a> {
+    pub(crate) notary_host: String,
+    pub(crate) notary_port: u16,
+    pub(crate) server_name: String,
+    pub(crate) max_sent_data: usize,
+    pub(crate) max_recv_data: usize,
+    pub(crate) notary_tls: bool,
+    pub(crate) notary_root_cert_pem: Option<Vec<u8>>,
+    pub(crate) timeout_seconds: u64,
+    pub(crate) crypto_provider: &'a str,
+    pub(crate) api_token: Option<String>,
+    pub(crate) log_level: &'a str,
+    pub(crate) runtime: Option<PyRef<'a, PyRuntime>>,
+    pub(crate) max_retries: u32,
+    pub(crate) retry_backoff_ms: u64,
+    pub(crate) notary_max_sent_data: Option<usize>,
+    pub(crate) notary_max_recv_data: Option<usize>,
+    pub(crate) defer_decryption: bool,
+    pub(crate) notary_cert_name: Option<String>,
+    pub(crate) notary_unix_socket: Option<String>,
+    pub(crate) notary_transport: &'a str,
+    pub(crate) client_cert_pem: Option<Vec<u8>>,
+    pub(crate) client_key_pem: Option<Vec<u8>>,
+    pub(crate) connect_timeout_seconds: u64,
+    pub(crate) worker_threads: Option<usize>,
+    pub(crate) connect_retries: u32,
+    pub(crate) notary_tcp_nodelay: bool,
+    pub(crate) notary_keepalive_seconds: Option<u64>,
+    pub(crate) max_recv_data_online: Option<usize>,
+    pub(crate) recv_slack_bytes: usize,
+    pub(crate) setup_ttl_seconds: Option<u64>,
+}
+
+impl ProverOptions<'_> {
+    /// The required arguments, with the defaults of the constructor for the rest.
+    pub(crate) fn new(notary_host: String, notary_port: u16, server_name: String) -> Self {
+        Self {
+            notary_host,
+            notary_port,
+            server_name,
+            max_sent_data: DEFAULT_MAX_SENT_DATA,
+            max_recv_data: DEFAULT_MAX_RECV_DATA,
+            notary_tls: false,
+            notary_root_cert_pem: None,
+            timeout_seconds: 0,
+            crypto_provider: "secp256k1",
+            api_token: None,
+            log_level: "info",
+            runtime: None,
+            max_retries: 0,
+            retry_backoff_ms: 500,
+            notary_max_sent_data: None,
+            notary_max_recv_data: None,
+            defer_decryption: true,
+            notary_cert_name: None,
+            notary_unix_socket: None,
+            notary_transport: "tcp",
+            client_cert_pem: None,
+            client_key_pem: None,
+            connect_timeout_seconds: 0,
+            worker_threads: None,
+            connect_retries: 0,
+            notary_tcp_nodelay: false,
+            notary_keepalive_seconds: None,
+            max_recv_data_online: None,
+            recv_slack_bytes: 0,
+            setup_ttl_seconds: None,
+        }
+    }
+}
+
+/// The optional arguments of `connect()`, all off by default.
+#[derive(Default)]
+pub(crate) struct ConnectOptions<'a> {
+    pub(crate) prefer_ipv4: bool,
+    pub(crate) proxy_url: Option<&'a str>,
+    pub(crate) pin_cert_sha256: Option<&'a str>,
+    pub(crate) tcp_nodelay: bool,
+    pub(crate) keepalive_seconds: Option<u64>,
+    pub(crate) sni: Option<&'a str>,
+    pub(crate) server_addr: Option<&'a str>,
+}
+
+/// The arguments of `finalize_notarize()`.
+pub(crate) struct NotarizeOptions<'a> {
+    pub(crate) verify_with_pub_key: Option<Vec<u8>>,
+    pub(crate) format: &'a str,
+    pub(crate) progress: Option<Py<PyAny>>,
+    pub(crate) hash_alg: &'a str,
+    pub(crate) commitments: Option<Vec<String>>,
+    pub(crate) secrets_passphrase: Option<String>,
+    pub(crate) sent_granularity: Option<usize>,
+    pub(crate) recv_granularity: Option<usize>,
+    pub(crate) auto_reset: bool,
+    pub(crate) max_attestation_bytes: Option<usize>,
+    pub(crate) sent_commit_ranges: Option<Vec<(usize, usize)>>,
+    pub(crate) recv_commit_ranges: Option<Vec<(usize, usize)>>,
+}
+
+impl Default for NotarizeOptions<'_> {
+    fn default() -> Self {
+        Self {
+            verify_with_pub_key: None,
+            format: "bincode",
+            progress: None,
+            hash_alg: "sha256",
+            commitments: None,
+            secrets_passphrase: None,
+            sent_granularity: None,
+            recv_granularity: None,
+            auto_reset: true,
+            max_attestation_bytes: None,
+            sent_commit_ranges: None,
+            recv_commit_ranges: None,
+        }
+    }
+}
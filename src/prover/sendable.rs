@@ -0,0 +1,399 @@
+//! A prover handle that may be moved and shared between Python threads.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+
+use crate::attestation;
+use crate::cancel::{Canceller, PyCancelHandle};
+use crate::errors::TlsnError;
+
+use super::{batch, header_pairs, PyProver};
+
+type Job = Box<dyn FnOnce(&mut PyProver) + Send>;
+
+/// A `PyProver` that lives on a dedicated worker thread.
+///
+/// The prover itself stays on the worker; every method call is sent there and the
+/// calling thread waits for the result with the GIL released. The handle can therefore
+/// be stored in shared data structures and used from any thread (e.g. a thread pool).
+/// Calls from several threads are queued and run one at a time, in order.
+///
+/// Takes the same constructor arguments as `PyProver` and offers its blocking methods.
+/// The worker thread exits once the handle is garbage collected.
+#[pyclass]
+pub struct PySendableProver {
+    jobs: Sender<Job>,
+    /// Kept outside the worker so a blocked call can be cancelled from another thread.
+    canceller: Canceller,
+}
+
+#[pymethods]
+impl PySendableProver {
+    #[new]
+    #[pyo3(signature = (*args, **kwargs))]
+    fn new(
+        py: Python<'_>,
+        args: &Bound<'_, PyTuple>,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        // Build through PyProver so both accept exactly the same arguments.
+        let template = py.get_type::<PyProver>().call(args, kwargs)?;
+        let mut prover = template.extract::<PyRef<'_, PyProver>>()?.detached();
+        let canceller = prover.canceller.clone();
+
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("tlsnpy-prover".into())
+            .spawn(move || {
+                for job in queue {
+                    job(&mut prover);
+                }
+            })
+            .map_err(|e| TlsnError::new_err(format!("Failed to start prover thread: {e}")))?;
+
+        Ok(Self { jobs, canceller })
+    }
+
+    /// See `PyProver.new_session`.
+    fn new_session(&self, py: Python<'_>) -> PyResult<()> {
+        self.call(py, |prover| prover.new_session())
+    }
+
+    /// Alias of `new_session`, kept for parity with `PyProver`.
+    fn reset(&self, py: Python<'_>) -> PyResult<()> {
+        self.new_session(py)
+    }
+
+    /// See `PyProver.connect`.
+    #[pyo3(signature = (
+        server_host,
+        server_port,
+        prefer_ipv4 = false,
+        proxy_url = None,
+        pin_cert_sha256 = None,
+        tcp_nodelay = false,
+        keepalive_seconds = None,
+        sni = None,
+        server_addr = None,
+    ))]
+    fn connect(
+        &self,
+        py: Python<'_>,
+        server_host: String,
+        server_port: u16,
+        prefer_ipv4: bool,
+        proxy_url: Option<String>,
+        pin_cert_sha256: Option<String>,
+        tcp_nodelay: bool,
+        keepalive_seconds: Option<u64>,
+        sni: Option<String>,
+        server_addr: Option<String>,
+    ) -> PyResult<()> {
+        self.call(py, move |prover| {
+            let (proxy_url, pin) = (proxy_url.as_deref(), pin_cert_sha256.as_deref());
+            prover.connect(
+                server_host,
+                server_port,
+                prefer_ipv4,
+                proxy_url,
+                pin,
+                tcp_nodelay,
+                keepalive_seconds,
+                sni.as_deref(),
+                server_addr.as_deref(),
+            )
+        })
+    }
+
+    /// See `PyProver.send_request`.
+    #[pyo3(signature = (raw_http, until_complete = false, keep_open = false))]
+    fn send_request(
+        &self,
+        py: Python<'_>,
+        raw_http: Vec<u8>,
+        until_complete: bool,
+        keep_open: bool,
+    ) -> PyResult<Vec<u8>> {
+        self.call(py, move |prover| prover.send_request(raw_http, until_complete, keep_open))
+    }
+
+    /// See `PyProver.send_http`.
+    #[pyo3(signature = (
+        method,
+        path,
+        headers = None,
+        body = None,
+        default_headers = true,
+        keep_open = false,
+        decompress = false,
+    ))]
+    fn send_http(
+        &self,
+        py: Python<'_>,
+        method: String,
+        path: String,
+        headers: Option<&Bound<'_, PyDict>>,
+        body: Option<Vec<u8>>,
+        default_headers: bool,
+        keep_open: bool,
+        decompress: bool,
+    ) -> PyResult<(u16, HashMap<String, String>, Vec<u8>)> {
+        let headers = header_pairs(headers)?;
+        self.call(py, move |prover| {
+            prover.http_exchange(
+                &method,
+                &path,
+                headers,
+                body,
+                default_headers,
+                keep_open,
+                decompress,
+            )
+        })
+    }
+
+    /// See `PyProver.close`.
+    fn close(&self, py: Python<'_>) -> PyResult<()> {
+        self.call(py, |prover| prover.close())
+    }
+
+    /// See `PyProver.stream_to`.
+    fn stream_to(
+        &self,
+        py: Python<'_>,
+        raw_http: Vec<u8>,
+        path: String,
+    ) -> PyResult<(u16, HashMap<String, String>)> {
+        self.call(py, move |prover| prover.stream_to(raw_http, &path))
+    }
+
+    /// See `PyProver.abort`.
+    fn abort(&self, py: Python<'_>) -> PyResult<()> {
+        self.call(py, |prover| {
+            prover.abort();
+            Ok(())
+        })
+    }
+
+    /// See `PyProver.start_notarize`.
+    fn start_notarize(&self, py: Python<'_>) -> PyResult<()> {
+        self.call(py, |prover| prover.start_notarize())
+    }
+
+    /// See `PyProver.finalize_notarize`.
+    #[pyo3(signature = (
+        verify_with_pub_key = None,
+        format = "bincode".to_string(),
+        progress = None,
+        hash_alg = "sha256".to_string(),
+        commitments = None,
+        secrets_passphrase = None,
+        sent_granularity = None,
+        recv_granularity = None,
+        auto_reset = true,
+        max_attestation_bytes = None,
+        sent_commit_ranges = None,
+        recv_commit_ranges = None,
+    ))]
+    fn finalize_notarize(
+        &self,
+        py: Python<'_>,
+        verify_with_pub_key: Option<Vec<u8>>,
+        format: String,
+        progress: Option<Py<PyAny>>,
+        hash_alg: String,
+        commitments: Option<Vec<String>>,
+        secrets_passphrase: Option<String>,
+        sent_granularity: Option<usize>,
+        recv_granularity: Option<usize>,
+        auto_reset: bool,
+        max_attestation_bytes: Option<usize>,
+        sent_commit_ranges: Option<Vec<(usize, usize)>>,
+        recv_commit_ranges: Option<Vec<(usize, usize)>>,
+    ) -> PyResult<(Vec<u8>, Vec<u8>)> {
+        self.call(py, move |prover| {
+            prover.finalize_notarize(
+                verify_with_pub_key,
+                &format,
+                progress,
+                &hash_alg,
+                commitments,
+                secrets_passphrase,
+                sent_granularity,
+                recv_granularity,
+                auto_reset,
+                max_attestation_bytes,
+                sent_commit_ranges,
+                recv_commit_ranges,
+            )
+        })
+    }
+
+    /// See `PyProver.write_attestation`.
+    #[pyo3(signature = (
+        attestation_path,
+        secrets_path,
+        verify_with_pub_key = None,
+        format = "bincode".to_string(),
+        progress = None,
+        hash_alg = "sha256".to_string(),
+        commitments = None,
+        sent_granularity = None,
+        recv_granularity = None,
+        auto_reset = true,
+        sent_commit_ranges = None,
+        recv_commit_ranges = None,
+    ))]
+    fn write_attestation(
+        &self,
+        py: Python<'_>,
+        attestation_path: String,
+        secrets_path: String,
+        verify_with_pub_key: Option<Vec<u8>>,
+        format: String,
+        progress: Option<Py<PyAny>>,
+        hash_alg: String,
+        commitments: Option<Vec<String>>,
+        sent_granularity: Option<usize>,
+        recv_granularity: Option<usize>,
+        auto_reset: bool,
+        sent_commit_ranges: Option<Vec<(usize, usize)>>,
+        recv_commit_ranges: Option<Vec<(usize, usize)>>,
+    ) -> PyResult<()> {
+        self.call(py, move |prover| {
+            prover.write_attestation(
+                &attestation_path,
+                &secrets_path,
+                verify_with_pub_key,
+                &format,
+                progress,
+                &hash_alg,
+                commitments,
+                sent_granularity,
+                recv_granularity,
+                auto_reset,
+                sent_commit_ranges,
+                recv_commit_ranges,
+            )
+        })
+    }
+
+    /// See `PyProver.notarize_many`.
+    #[pyo3(signature = (requests, verify_with_pub_key = None, format = "bincode".to_string()))]
+    fn notarize_many<'py>(
+        &self,
+        py: Python<'py>,
+        requests: Vec<Bound<'py, PyDict>>,
+        verify_with_pub_key: Option<Vec<u8>>,
+        format: String,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let specs = batch::parse_specs(&requests)?;
+        attestation::Format::parse(&format)?;
+        let outcomes = self.call(py, move |prover| {
+            batch::run(prover, specs, verify_with_pub_key, &format)
+        })?;
+        batch::to_dicts(py, outcomes)
+    }
+
+    /// See `PyProver.server_cert_chain`.
+    fn server_cert_chain(&self, py: Python<'_>) -> PyResult<Vec<Vec<u8>>> {
+        self.call(py, |prover| prover.server_cert_chain())
+    }
+
+    /// See `PyProver.tls_info`.
+    fn tls_info(&self, py: Python<'_>) -> PyResult<HashMap<String, String>> {
+        self.call(py, |prover| prover.tls_info())
+    }
+
+    /// See `PyProver.state`.
+    fn state(&self, py: Python<'_>) -> PyResult<&'static str> {
+        self.call(py, |prover| Ok(prover.state()))
+    }
+
+    /// See `PyProver.session_id`.
+    fn session_id(&self, py: Python<'_>) -> PyResult<String> {
+        self.call(py, |prover| prover.session_id())
+    }
+
+    /// See `PyProver.protocol_version`.
+    fn protocol_version(&self, py: Python<'_>) -> PyResult<String> {
+        self.call(py, |prover| prover.protocol_version())
+    }
+
+    /// See `PyProver.session_age_seconds`.
+    fn session_age_seconds(&self, py: Python<'_>) -> PyResult<f64> {
+        self.call(py, |prover| prover.session_age_seconds())
+    }
+
+    /// See `PyProver.negotiated_limits`.
+    fn negotiated_limits(&self, py: Python<'_>) -> PyResult<HashMap<String, usize>> {
+        self.call(py, |prover| prover.negotiated_limits())
+    }
+
+    /// See `PyProver.committed_transcript`.
+    fn committed_transcript(&self, py: Python<'_>) -> PyResult<(Vec<u8>, Vec<u8>)> {
+        self.call(py, |prover| prover.committed_transcript())
+    }
+
+    /// See `PyProver.sent_len`.
+    fn sent_len(&self, py: Python<'_>) -> PyResult<usize> {
+        self.call(py, |prover| prover.sent_len())
+    }
+
+    /// See `PyProver.recv_len`.
+    fn recv_len(&self, py: Python<'_>) -> PyResult<usize> {
+        self.call(py, |prover| prover.recv_len())
+    }
+
+    /// Cancels the call running on the worker, which then raises `InterruptedError`.
+    fn cancel(&self) {
+        self.canceller.cancel();
+    }
+
+    /// See `PyProver.cancel_handle`.
+    fn cancel_handle(&self) -> PyCancelHandle {
+        PyCancelHandle::new(self.canceller.clone())
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Discards any session in progress when leaving the `with` block.
+    fn __exit__(
+        &self,
+        py: Python<'_>,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        self.call(py, |prover| {
+            prover.abort();
+            Ok(())
+        })
+    }
+}
+
+impl PySendableProver {
+    /// Runs `f` on the worker thread and waits for its result with the GIL released.
+    fn call<T, F>(&self, py: Python<'_>, f: F) -> PyResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut PyProver) -> PyResult<T> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let job: Job = Box::new(move |prover| {
+            // The caller may have gone away; there is no one left to report to then
+            let _ = tx.send(f(prover));
+        });
+
+        // Both fail only if the worker panicked
+        let exited = || TlsnError::new_err("Prover worker thread has exited");
+        self.jobs.send(job).map_err(|_| exited())?;
+        py.allow_threads(|| rx.recv()).map_err(|_| exited())?
+    }
+}
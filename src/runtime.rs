@@ -0,0 +1,148 @@
+use std::ops::Deref;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple, PyType};
+
+use tokio::runtime::{Builder, Runtime};
+
+use crate::errors::TlsnError;
+
+/// Worker threads of a runtime created without an explicit `worker_threads`.
+///
+/// Kept small since a process may run dozens of provers, each with its own runtime.
+pub(crate) const DEFAULT_WORKER_THREADS: usize = 2;
+/// How often the driver of a current-thread runtime hands the runtime back, so it
+/// notices once the runtime is no longer used.
+const DRIVER_INTERVAL: Duration = Duration::from_secs(1);
+/// How long dropping the last reference to a runtime waits for its tasks to stop.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A reference to a runtime shared by provers, notaries and `PyRuntime`s.
+///
+/// Dropping the last one shuts the runtime down, waiting up to `SHUTDOWN_TIMEOUT`
+/// rather than for blocking tasks indefinitely. Within an async context, where a
+/// blocking shutdown panics, it shuts down in the background instead. That happens when
+/// Python collects a prover or notary while running code called from another runtime,
+/// such as a signal handler or progress callback.
+#[derive(Clone)]
+pub(crate) struct SharedRuntime(Option<Arc<Runtime>>);
+
+impl Deref for SharedRuntime {
+    type Target = Runtime;
+
+    fn deref(&self) -> &Runtime {
+        self.0.as_ref().expect("the runtime is only taken when dropped")
+    }
+}
+
+impl Drop for SharedRuntime {
+    fn drop(&mut self) {
+        let Some(runtime) = self.0.take().and_then(Arc::into_inner) else {
+            return;
+        };
+        if tokio::runtime::Handle::try_current().is_ok() {
+            runtime.shutdown_background();
+        } else {
+            runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+/// A Tokio runtime that can be shared by many provers and notaries.
+///
+/// By default every `PyProver` and `PyNotary` creates its own runtime with
+/// `DEFAULT_WORKER_THREADS` threads, or `worker_threads` if given. When fanning out many
+/// instances, create one `PyRuntime` and pass it via `runtime=` (or the `with_runtime`
+/// constructors) so they share its worker threads.
+#[pyclass]
+pub struct PyRuntime {
+    rt: SharedRuntime,
+}
+
+#[pymethods]
+impl PyRuntime {
+    /// `worker_threads` defaults to the number of CPU cores, since a shared runtime
+    /// serves many instances.
+    #[new]
+    #[pyo3(signature = (worker_threads = None))]
+    fn new(worker_threads: Option<usize>) -> PyResult<Self> {
+        let worker_threads = worker_threads.map_or_else(default_parallelism, Ok)?;
+        Ok(Self { rt: new_runtime(worker_threads)? })
+    }
+}
+
+/// Returns the shared runtime if one was given, otherwise a new dedicated one with
+/// `worker_threads` threads.
+pub(crate) fn shared_or_new(
+    runtime: Option<PyRef<'_, PyRuntime>>,
+    worker_threads: Option<usize>,
+) -> PyResult<SharedRuntime> {
+    match (runtime, worker_threads) {
+        (Some(_), Some(_)) => Err(PyValueError::new_err(
+            "worker_threads can't be set together with a shared runtime",
+        )),
+        (Some(runtime), None) => Ok(runtime.rt.clone()),
+        (None, worker_threads) => new_runtime(worker_threads.unwrap_or(DEFAULT_WORKER_THREADS)),
+    }
+}
+
+fn default_parallelism() -> PyResult<usize> {
+    std::thread::available_parallelism()
+        .map(usize::from)
+        .map_err(|e| TlsnError::new_err(format!("Failed to count CPU cores: {e}")))
+}
+
+/// Creates a runtime, raising instead of panicking if e.g. threads can't be spawned.
+///
+/// One worker thread selects a current-thread runtime. Its tasks only run while some
+/// thread blocks on it, so a driver thread does that for as long as the runtime is in
+/// use; provers and notaries spawn tasks that must progress between calls.
+fn new_runtime(worker_threads: usize) -> PyResult<SharedRuntime> {
+    let runtime = match worker_threads {
+        0 => return Err(PyValueError::new_err("worker_threads must be at least 1")),
+        1 => Builder::new_current_thread().enable_all().build(),
+        _ => Builder::new_multi_thread().worker_threads(worker_threads).enable_all().build(),
+    };
+    let runtime = runtime
+        .map(Arc::new)
+        .map_err(|e| TlsnError::new_err(format!("Failed to create Tokio runtime: {e}")))?;
+
+    if worker_threads == 1 {
+        let weak = Arc::downgrade(&runtime);
+        std::thread::Builder::new()
+            .name("tlsnpy-runtime".to_string())
+            .spawn(move || drive(weak))
+            .map_err(|e| TlsnError::new_err(format!("Failed to create Tokio runtime: {e}")))?;
+    }
+    Ok(SharedRuntime(Some(runtime)))
+}
+
+/// Runs the tasks of a current-thread runtime until every other reference is gone.
+fn drive(runtime: Weak<Runtime>) {
+    while let Some(runtime) = runtime.upgrade() {
+        runtime.block_on(tokio::time::sleep(DRIVER_INTERVAL));
+        // The last `SharedRuntime` went while the driver held this reference
+        if let Some(runtime) = Arc::into_inner(runtime) {
+            runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+/// Implements `cls.with_runtime(runtime, *args, **kwargs)` by forwarding to
+/// `cls(*args, runtime=runtime, **kwargs)`.
+pub(crate) fn construct_with_runtime<'py>(
+    cls: &Bound<'py, PyType>,
+    runtime: Py<PyRuntime>,
+    args: &Bound<'py, PyTuple>,
+    kwargs: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let kwargs = match kwargs {
+        Some(kwargs) => kwargs.copy()?,
+        None => PyDict::new(cls.py()),
+    };
+    kwargs.set_item("runtime", runtime)?;
+    cls.call(args, Some(&kwargs))
+}
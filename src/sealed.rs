@@ -0,0 +1,59 @@
+//! Passphrase encryption of serialized secrets, so they can be stored safely at rest.
+//!
+//! A sealed blob is `MAGIC || salt || nonce || ciphertext`. The key is derived from the
+//! passphrase with Argon2id and the secrets are encrypted with XChaCha20-Poly1305, the
+//! header being authenticated as associated data.
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+const MAGIC: &[u8] = b"tlsnpy-sealed-1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+
+/// Whether `bytes` were produced by `seal`.
+pub(crate) fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Encrypts `plaintext` with a key derived from `passphrase`.
+pub(crate) fn seal(plaintext: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    if passphrase.is_empty() {
+        anyhow::bail!("passphrase must not be empty");
+    }
+
+    let mut header = [0u8; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    OsRng.fill_bytes(&mut header[MAGIC.len()..]);
+    let (salt, nonce) = header[MAGIC.len()..].split_at(SALT_LEN);
+
+    let ciphertext = cipher(passphrase, salt)?
+        .encrypt(XNonce::from_slice(nonce), Payload { msg: plaintext, aad: &header })
+        .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+    Ok([&header[..], &ciphertext].concat())
+}
+
+/// Decrypts a blob produced by `seal`, failing if the passphrase is wrong or the blob
+/// was tampered with.
+pub(crate) fn open(sealed: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    if !is_sealed(sealed) || sealed.len() < HEADER_LEN {
+        anyhow::bail!("not encrypted secrets");
+    }
+    let (header, ciphertext) = sealed.split_at(HEADER_LEN);
+    let (salt, nonce) = header[MAGIC.len()..].split_at(SALT_LEN);
+
+    cipher(passphrase, salt)?
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| anyhow::anyhow!("wrong passphrase or corrupted secrets"))
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> anyhow::Result<XChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("deriving the key failed: {e}"))?;
+    Ok(XChaCha20Poly1305::new(&key.into()))
+}
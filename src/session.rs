@@ -0,0 +1,207 @@
+//! Requests notarization sessions without `NotaryClient`, for notaries it can't reach.
+//!
+//! `NotaryClient` only connects over TCP and verifies the notary's certificate against
+//! the host it connects to, which fails behind load balancers and CDNs and rules out
+//! Unix sockets. This speaks the same two-step protocol:
+//! `POST /session` to configure a session, then `GET /notarize` upgraded to a raw TCP
+//! stream or a WebSocket that carries the MPC.
+
+use std::time::Instant;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::rustls::RootCertStore;
+use tokio_util::compat::{FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt};
+use ws_stream_tungstenite::WsStream;
+
+use crate::errors::Rejection;
+use crate::{http, net};
+
+/// Upper bound on the size of the notary's reply to `POST /session`.
+const MAX_SESSION_RESPONSE: usize = 64 * 1024;
+
+/// The connection a notarization runs over.
+pub(crate) trait NotaryIo: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> NotaryIo for T {}
+
+/// How the notarization connection is carried once the session is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transport {
+    /// `GET /notarize` upgraded to a raw TCP stream.
+    Tcp,
+    /// `GET /notarize` upgraded to a WebSocket, as browser clients use.
+    WebSocket,
+}
+
+impl Transport {
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "tcp" => Some(Self::Tcp),
+            "websocket" => Some(Self::WebSocket),
+            _ => None,
+        }
+    }
+
+    /// The notary's name for the transport in session requests.
+    fn client_type(self) -> &'static str {
+        match self {
+            Self::Tcp => "Tcp",
+            Self::WebSocket => "Websocket",
+        }
+    }
+}
+
+/// A notarization session the notary accepted.
+#[derive(Debug, Clone)]
+pub(crate) struct Accepted {
+    /// Id the notary assigned to the session, as found in its logs.
+    pub(crate) id: String,
+    /// Name the prover of the session verifies the server as.
+    pub(crate) server_name: String,
+    /// Data limits of the session. The notary rejects requests for more than it allows,
+    /// so an accepted session has exactly the limits requested.
+    pub(crate) max_sent_data: usize,
+    pub(crate) max_recv_data: usize,
+    /// tlsn protocol version the session is set up with.
+    pub(crate) protocol_version: String,
+    /// When the notary accepted the session, which its timeout counts from.
+    pub(crate) accepted_at: Instant,
+}
+
+/// Where and how to reach the notary.
+pub(crate) struct NotaryEndpoint {
+    /// Sent as `Host`, and connected to unless `unix_socket` is set.
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    /// Socket to connect to instead of `host:port`.
+    pub(crate) unix_socket: Option<String>,
+    /// TLS settings, or `None` for a plain connection.
+    pub(crate) tls: Option<NotaryTls>,
+    pub(crate) transport: Transport,
+    pub(crate) api_key: Option<String>,
+    /// Applied to TCP connections to the notary.
+    pub(crate) socket: net::SocketOptions,
+}
+
+pub(crate) struct NotaryTls {
+    /// Name the notary's certificate is verified against.
+    pub(crate) cert_name: String,
+    pub(crate) root_store: Option<RootCertStore>,
+}
+
+/// Configures a session with the notary and opens its notarization connection,
+/// returning the session id and the connection.
+pub(crate) async fn request_notarization(
+    endpoint: &NotaryEndpoint,
+    max_sent_data: usize,
+    max_recv_data: usize,
+) -> anyhow::Result<(String, Box<dyn NotaryIo>)> {
+    let body = serde_json::json!({
+        "clientType": endpoint.transport.client_type(),
+        "maxSentData": max_sent_data,
+        "maxRecvData": max_recv_data,
+    })
+    .to_string();
+    let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+    if let Some(api_key) = &endpoint.api_key {
+        headers.push(("Authorization".to_string(), api_key.clone()));
+    }
+    let headers = http::with_default_headers(headers, &endpoint.host);
+    let request = http::build_request("POST", "/session", &headers, Some(body.as_bytes()))?;
+
+    let mut stream = connect(endpoint).await?;
+    let response = net::round_trip(&mut stream, &request, MAX_SESSION_RESPONSE).await?;
+    if response.status != 200 {
+        let rejection = Rejection::from_response(response.status, &response.body);
+        return Err(anyhow::Error::new(rejection).context("notary rejected the session request"));
+    }
+    let session: serde_json::Value = serde_json::from_slice(&response.body)?;
+    let session_id = session["sessionId"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("notary response has no sessionId"))?
+        .to_string();
+
+    let stream = connect(endpoint).await?;
+    let stream = match endpoint.transport {
+        Transport::Tcp => upgrade_tcp(stream, &endpoint.host, &session_id).await?,
+        Transport::WebSocket => upgrade_websocket(stream, endpoint, &session_id).await?,
+    };
+
+    Ok((session_id, stream))
+}
+
+async fn connect(endpoint: &NotaryEndpoint) -> anyhow::Result<Box<dyn NotaryIo>> {
+    let stream: Box<dyn NotaryIo> = match &endpoint.unix_socket {
+        #[cfg(unix)]
+        Some(path) => Box::new(tokio::net::UnixStream::connect(path).await?),
+        #[cfg(not(unix))]
+        Some(_) => anyhow::bail!("Unix sockets are not supported on this platform"),
+        None => {
+            let stream = net::connect_tcp(&endpoint.host, endpoint.port, false).await?;
+            endpoint.socket.apply(&stream)?;
+            Box::new(stream)
+        }
+    };
+
+    match &endpoint.tls {
+        Some(tls) => {
+            let stream = net::tls_client(stream, &tls.cert_name, tls.root_store.clone()).await?;
+            Ok(Box::new(stream))
+        }
+        None => Ok(stream),
+    }
+}
+
+/// Upgrades the `/notarize` request to a raw TCP stream.
+async fn upgrade_tcp(
+    mut stream: Box<dyn NotaryIo>,
+    host: &str,
+    session_id: &str,
+) -> anyhow::Result<Box<dyn NotaryIo>> {
+    let upgrade = format!(
+        "GET /notarize?sessionId={session_id} HTTP/1.1\r\nHost: {host}\r\n\
+         Connection: Upgrade\r\nUpgrade: TCP\r\n\r\n"
+    );
+    stream.write_all(upgrade.as_bytes()).await?;
+    stream.flush().await?;
+    let head = net::read_head(&mut stream).await?;
+    let (code, reason) = net::status_line(&head)?;
+    if code != 101 {
+        let rejection = match rejection_body(&mut stream, &head).await {
+            Some(body) if !body.is_empty() => Rejection::from_response(code, &body),
+            _ => Rejection { status: code, reason },
+        };
+        let err = anyhow::Error::new(rejection);
+        return Err(err.context("notary refused to start the notarization"));
+    }
+    Ok(stream)
+}
+
+/// Reads the body of an error response to the upgrade, if it is framed with
+/// `Content-Length` and small enough to hold a reason.
+async fn rejection_body(stream: &mut Box<dyn NotaryIo>, head: &[u8]) -> Option<Vec<u8>> {
+    let (response, _) = http::parse_head(head).ok()??;
+    let len: usize = response.header("content-length")?.trim().parse().ok()?;
+    if len > MAX_SESSION_RESPONSE {
+        return None;
+    }
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).await.ok()?;
+    Some(body)
+}
+
+/// Upgrades the `/notarize` request to a WebSocket and adapts its binary messages to a
+/// byte stream.
+async fn upgrade_websocket(
+    stream: Box<dyn NotaryIo>,
+    endpoint: &NotaryEndpoint,
+    session_id: &str,
+) -> anyhow::Result<Box<dyn NotaryIo>> {
+    let scheme = if endpoint.tls.is_some() { "wss" } else { "ws" };
+    let url = format!(
+        "{scheme}://{}:{}/notarize?sessionId={session_id}",
+        endpoint.host, endpoint.port
+    );
+    let (ws, _) = async_tungstenite::client_async(url, stream.compat()).await?;
+    Ok(Box::new(WsStream::new(ws).compat()))
+}
@@ -0,0 +1,212 @@
+use std::ops::Range;
+
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyDict;
+
+use tlsn_core::attestation::Attestation;
+use tlsn_core::presentation::{Presentation, PresentationOutput};
+use tlsn_core::signing::VerifyingKey;
+use tlsn_core::CryptoProvider;
+
+use crate::attestation;
+use crate::errors::TlsnError;
+use crate::keys::{self, parse_public_key_pem, PublicKey};
+
+/// The `notary_pub_key_pem` of `verify`: one PEM public key or a list of trusted ones.
+#[derive(FromPyObject)]
+enum NotaryKeys {
+    One(Vec<u8>),
+    Many(Vec<Vec<u8>>),
+}
+
+impl NotaryKeys {
+    fn parse(self) -> PyResult<Vec<PublicKey>> {
+        match self {
+            Self::One(pem) => Ok(vec![parse_public_key_pem(&pem).map_err(|e| {
+                PyValueError::new_err(format!("Invalid notary public key: {e}"))
+            })?]),
+            Self::Many(pems) if pems.is_empty() => {
+                Err(PyValueError::new_err("notary_pub_key_pem must list at least one key"))
+            }
+            Self::Many(pems) => pems
+                .iter()
+                .enumerate()
+                .map(|(index, pem)| {
+                    parse_public_key_pem(pem).map_err(|e| {
+                        PyValueError::new_err(format!("Invalid notary public key {index}: {e}"))
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Verifies attestations produced by a notary.
+///
+/// Verification is purely local: it needs the serialized attestation and the notary's
+/// public key, but no connection to the notary or the server.
+#[pyclass]
+pub struct PyVerifier {}
+
+#[pymethods]
+impl PyVerifier {
+    #[new]
+    fn new() -> Self {
+        Self {}
+    }
+
+    /// Verifies a serialized attestation (bincode or JSON) against the notary's PEM public
+    /// key, or a list of them for a verifier that trusts several notaries.
+    ///
+    /// Returns a dict with the transcript lengths (`sent_len`, `recv_len`), the session
+    /// `time`, `server_name`, the `attestation_version` of the format and the
+    /// `notary_key_fingerprint` of the key that signed it: the hex SHA-256 of its DER
+    /// SubjectPublicKeyInfo, as `openssl pkey -pubin -outform DER | sha256sum` prints it.
+    /// The attestation only commits to the server name, so `server_name` is `None`
+    /// unless it is revealed through a presentation.
+    ///
+    /// With a list, verification fails only if none of the keys signed the attestation;
+    /// any of them being malformed raises `ValueError` naming its index. Raises
+    /// `VersionMismatchError` for an attestation in a format version this build doesn't
+    /// read, i.e. one produced by an incompatible tlsn release.
+    fn verify<'py>(
+        &self,
+        py: Python<'py>,
+        attestation_bytes: Vec<u8>,
+        notary_pub_key_pem: NotaryKeys,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let attestation = attestation::decode(&attestation_bytes)?;
+        let trusted = notary_pub_key_pem.parse()?;
+        let (PresentationOutput { server_name, connection_info, .. }, key) =
+            check_attestation_trusted(&attestation, &trusted)?;
+        let fingerprint = keys::fingerprint(key)
+            .map_err(|e| PyValueError::new_err(format!("Invalid notary public key: {e}")))?;
+
+        let result = PyDict::new(py);
+        result.set_item("sent_len", connection_info.transcript_length.sent)?;
+        result.set_item("recv_len", connection_info.transcript_length.received)?;
+        result.set_item("time", connection_info.time)?;
+        result.set_item("server_name", server_name.map(|name| name.to_string()))?;
+        result.set_item("attestation_version", attestation::check_version(&attestation)?)?;
+        result.set_item("notary_key_fingerprint", fingerprint)?;
+        Ok(result)
+    }
+
+    /// Verifies a serialized presentation (bincode or JSON) against the notary's PEM
+    /// public key and returns what it proves.
+    ///
+    /// Like `verify`, the dict has `sent_len`, `recv_len`, `time` and `server_name`
+    /// (`None` if the presentation doesn't reveal it). `sent` and `recv` list the
+    /// revealed parts of each direction as `(start, end, data)` tuples; redacted bytes
+    /// are not in any range. Raises `TlsnError` if the signature, the server identity
+    /// proof or a transcript commitment doesn't check out, and `VersionMismatchError`
+    /// if the attestation it was built from has another format version.
+    #[staticmethod]
+    fn verify_presentation<'py>(
+        py: Python<'py>,
+        presentation_bytes: Vec<u8>,
+        notary_pub_key_pem: Vec<u8>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let presentation = attestation::decode_presentation(&presentation_bytes)?;
+        attestation::check_presentation_version(&presentation)?;
+        let expected_key = parse_public_key_pem(&notary_pub_key_pem)
+            .map_err(|e| PyValueError::new_err(format!("Invalid notary public key: {e}")))?;
+        check_key(&presentation, &expected_key)?;
+
+        let PresentationOutput { server_name, connection_info, transcript, .. } = presentation
+            .verify(&CryptoProvider::default())
+            .map_err(|e| TlsnError::new_err(format!("Verification failed: {e}")))?;
+
+        let (sent, recv) = match &transcript {
+            Some(transcript) => (
+                revealed(transcript.sent_unsafe(), transcript.sent_authed().iter_ranges()),
+                revealed(
+                    transcript.received_unsafe(),
+                    transcript.received_authed().iter_ranges(),
+                ),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+
+        let result = PyDict::new(py);
+        result.set_item("sent_len", connection_info.transcript_length.sent)?;
+        result.set_item("recv_len", connection_info.transcript_length.received)?;
+        result.set_item("time", connection_info.time)?;
+        result.set_item("server_name", server_name.map(|name| name.to_string()))?;
+        result.set_item("sent", sent)?;
+        result.set_item("recv", recv)?;
+        Ok(result)
+    }
+}
+
+/// The authenticated ranges of `data`, as `(start, end, bytes)`.
+fn revealed(
+    data: &[u8],
+    authed: impl Iterator<Item = Range<usize>>,
+) -> Vec<(usize, usize, Vec<u8>)> {
+    authed
+        .map(|range| (range.start, range.end, data[range].to_vec()))
+        .collect()
+}
+
+/// Checks that a deserialized attestation is in a format version this build reads and
+/// signed by the notary with the given PEM public key.
+pub(crate) fn check_attestation(
+    attestation: &Attestation,
+    notary_pub_key_pem: &[u8],
+) -> PyResult<PresentationOutput> {
+    let expected_key = parse_public_key_pem(notary_pub_key_pem)
+        .map_err(|e| PyValueError::new_err(format!("Invalid notary public key: {e}")))?;
+    let (output, _) = check_attestation_trusted(attestation, std::slice::from_ref(&expected_key))?;
+    Ok(output)
+}
+
+/// Like `check_attestation`, but accepts a signature by any of the `trusted` keys and
+/// returns the one that made it.
+fn check_attestation_trusted<'k>(
+    attestation: &Attestation,
+    trusted: &'k [PublicKey],
+) -> PyResult<(PresentationOutput, &'k PublicKey)> {
+    attestation::check_version(attestation)?;
+
+    let provider = CryptoProvider::default();
+    // A presentation without identity or transcript proofs only proves the
+    // attestation itself, which is exactly what is checked here.
+    let presentation: Presentation = attestation
+        .presentation_builder(&provider)
+        .build()
+        .map_err(|e| TlsnError::new_err(format!("Verification failed: {e}")))?;
+
+    let key = match trusted {
+        [expected] => check_key(&presentation, expected).map(|()| expected)?,
+        _ => trusted
+            .iter()
+            .find(|expected| same_key(presentation.verifying_key(), expected))
+            .ok_or_else(|| {
+                TlsnError::new_err(format!(
+                    "Verification failed: attestation was not signed by any of the {} trusted \
+                     notary keys",
+                    trusted.len()
+                ))
+            })?,
+    };
+
+    let output = presentation
+        .verify(&provider)
+        .map_err(|e| TlsnError::new_err(format!("Verification failed: {e}")))?;
+    Ok((output, key))
+}
+
+fn check_key(presentation: &Presentation, expected: &PublicKey) -> PyResult<()> {
+    if !same_key(presentation.verifying_key(), expected) {
+        return Err(TlsnError::new_err(
+            "Verification failed: attestation was not signed by the given notary key",
+        ));
+    }
+    Ok(())
+}
+
+fn same_key(key: &VerifyingKey, expected: &PublicKey) -> bool {
+    key.alg == expected.alg && key.data == expected.data
+}
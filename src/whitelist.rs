@@ -0,0 +1,132 @@
+//! The notary's authorization whitelist, held in memory so it can change while the
+//! notary runs.
+//!
+//! The file uses the notary server's CSV format: a header row naming at least the
+//! `ApiKey` column, conventionally `Name,ApiKey,CreatedAt`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{PoisonError, RwLock};
+
+use anyhow::Context;
+use tempfile::NamedTempFile;
+
+const API_KEY_COLUMN: &str = "ApiKey";
+
+/// API keys admitted by the notary, backed by a CSV file.
+#[derive(Debug)]
+pub(crate) struct Whitelist {
+    path: String,
+    tokens: RwLock<HashSet<String>>,
+}
+
+impl Whitelist {
+    /// Loads the whitelist from the CSV file at `path`.
+    pub(crate) fn load(path: &str) -> anyhow::Result<Self> {
+        let (_, records) = read(path)?;
+        Ok(Self {
+            path: path.to_string(),
+            tokens: RwLock::new(tokens(&records)),
+        })
+    }
+
+    /// Whether `token` is admitted.
+    pub(crate) fn contains(&self, token: &str) -> bool {
+        self.tokens.read().unwrap_or_else(PoisonError::into_inner).contains(token)
+    }
+
+    /// Replaces the admitted keys with those now in the file, returning how many there
+    /// are. The keys stay unchanged if the file can't be read.
+    pub(crate) fn reload(&self) -> anyhow::Result<usize> {
+        let mut tokens = self.tokens.write().unwrap_or_else(PoisonError::into_inner);
+        let (_, records) = read(&self.path)?;
+        *tokens = self::tokens(&records);
+        Ok(tokens.len())
+    }
+
+    /// Admits `token`, recording it in the file under `name`. Returns false if the file
+    /// already lists it.
+    pub(crate) fn add(&self, token: &str, name: &str) -> anyhow::Result<bool> {
+        let mut tokens = self.tokens.write().unwrap_or_else(PoisonError::into_inner);
+        let (headers, mut records) = read(&self.path)?;
+        let added = !records.iter().any(|record| record.api_key == token);
+
+        if added {
+            let created_at =
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+            let fields = headers
+                .iter()
+                .map(|header| match header.trim() {
+                    API_KEY_COLUMN => token,
+                    "Name" => name,
+                    "CreatedAt" => created_at.as_str(),
+                    _ => "",
+                })
+                .collect();
+            records.push(Record { api_key: token.to_string(), fields });
+            write(&self.path, &headers, &records)?;
+        }
+        *tokens = self::tokens(&records);
+        Ok(added)
+    }
+
+    /// Stops admitting `token` and removes it from the file. Returns false if the file
+    /// didn't list it.
+    pub(crate) fn remove(&self, token: &str) -> anyhow::Result<bool> {
+        let mut tokens = self.tokens.write().unwrap_or_else(PoisonError::into_inner);
+        let (headers, mut records) = read(&self.path)?;
+        let before = records.len();
+        records.retain(|record| record.api_key != token);
+        let removed = records.len() != before;
+
+        if removed {
+            write(&self.path, &headers, &records)?;
+        }
+        *tokens = self::tokens(&records);
+        Ok(removed)
+    }
+}
+
+struct Record {
+    api_key: String,
+    fields: csv::StringRecord,
+}
+
+fn tokens(records: &[Record]) -> HashSet<String> {
+    records.iter().map(|record| record.api_key.clone()).collect()
+}
+
+fn read(path: &str) -> anyhow::Result<(csv::StringRecord, Vec<Record>)> {
+    let mut reader = csv::Reader::from_path(path).with_context(|| format!("reading {path}"))?;
+    let headers = reader.headers()?.clone();
+    let column = headers
+        .iter()
+        .position(|header| header.trim() == API_KEY_COLUMN)
+        .ok_or_else(|| anyhow::anyhow!("{path} has no {API_KEY_COLUMN} column"))?;
+
+    let mut records = Vec::new();
+    for fields in reader.records() {
+        let fields = fields.with_context(|| format!("reading {path}"))?;
+        let api_key = fields.get(column).unwrap_or_default().trim().to_string();
+        if !api_key.is_empty() {
+            records.push(Record { api_key, fields });
+        }
+    }
+    Ok((headers, records))
+}
+
+/// Rewrites the file through a temporary file in the same directory, so readers see
+/// either the old or the new contents.
+fn write(path: &str, headers: &csv::StringRecord, records: &[Record]) -> anyhow::Result<()> {
+    let dir = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty());
+    let file = NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))?;
+
+    let mut writer = csv::Writer::from_writer(file);
+    writer.write_record(headers)?;
+    for record in records {
+        writer.write_record(&record.fields)?;
+    }
+    let file = writer.into_inner().map_err(|e| anyhow::anyhow!("writing {path}: {e}"))?;
+    file.persist(path).with_context(|| format!("writing {path}"))?;
+    Ok(())
+}
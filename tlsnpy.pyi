@@ -0,0 +1,371 @@
+# Type stubs for the tlsnpy extension module.
+#
+# Kept in sync with the #[pymethods] and #[pyfunction] signatures in src/ by hand;
+# scripts/check_stubs.py compares them against the built module and runs in CI.
+# A verify-only build provides just PyVerifier, the exceptions and the attestation
+# functions.
+
+from typing import Any, Callable, Optional, Union
+
+Progress = Callable[[str, float], object]
+
+class TlsnError(RuntimeError): ...
+class NotaryConnectionError(TlsnError): ...
+class AuthorizationError(TlsnError): ...
+class ProtocolLimitError(TlsnError): ...
+class NotaryBusyError(TlsnError): ...
+class VersionMismatchError(TlsnError): ...
+class TimeoutError(TlsnError): ...
+
+class PyRuntime:
+    def __init__(self, worker_threads: Optional[int] = None) -> None: ...
+
+class PyCancelHandle:
+    def cancel(self) -> None: ...
+
+class PyProver:
+    def __init__(
+        self,
+        notary_host: str,
+        notary_port: int,
+        server_name: str,
+        max_sent_data: int = 10000,
+        max_recv_data: int = 10000,
+        notary_tls: bool = False,
+        notary_root_cert_pem: Optional[bytes] = None,
+        timeout_seconds: int = 0,
+        crypto_provider: str = "secp256k1",
+        api_token: Optional[str] = None,
+        log_level: str = "info",
+        runtime: Optional[PyRuntime] = None,
+        max_retries: int = 0,
+        retry_backoff_ms: int = 500,
+        notary_max_sent_data: Optional[int] = None,
+        notary_max_recv_data: Optional[int] = None,
+        defer_decryption: bool = True,
+        notary_cert_name: Optional[str] = None,
+        notary_unix_socket: Optional[str] = None,
+        notary_transport: str = "tcp",
+        client_cert_pem: Optional[bytes] = None,
+        client_key_pem: Optional[bytes] = None,
+        connect_timeout_seconds: int = 0,
+        worker_threads: Optional[int] = None,
+        connect_retries: int = 0,
+        notary_tcp_nodelay: bool = False,
+        notary_keepalive_seconds: Optional[int] = None,
+        max_recv_data_online: Optional[int] = None,
+        recv_slack_bytes: int = 0,
+        setup_ttl_seconds: Optional[int] = None,
+    ) -> None: ...
+    @classmethod
+    def with_runtime(cls, runtime: PyRuntime, *args: Any, **kwargs: Any) -> "PyProver": ...
+    def new_session(self) -> None: ...
+    def reset(self) -> None: ...
+    def new_session_async(self) -> Any: ...
+    def reset_async(self) -> Any: ...
+    def connect(
+        self,
+        server_host: str,
+        server_port: int,
+        prefer_ipv4: bool = False,
+        proxy_url: Optional[str] = None,
+        pin_cert_sha256: Optional[str] = None,
+        tcp_nodelay: bool = False,
+        keepalive_seconds: Optional[int] = None,
+        sni: Optional[str] = None,
+        server_addr: Optional[str] = None,
+    ) -> None: ...
+    def connect_async(
+        self,
+        server_host: str,
+        server_port: int,
+        prefer_ipv4: bool = False,
+        proxy_url: Optional[str] = None,
+        pin_cert_sha256: Optional[str] = None,
+        tcp_nodelay: bool = False,
+        keepalive_seconds: Optional[int] = None,
+        sni: Optional[str] = None,
+        server_addr: Optional[str] = None,
+    ) -> Any: ...
+    def send_request(
+        self, raw_http: bytes, until_complete: bool = False, keep_open: bool = False
+    ) -> bytes: ...
+    def send_http(
+        self,
+        method: str,
+        path: str,
+        headers: Optional[dict[str, str]] = None,
+        body: Optional[bytes] = None,
+        default_headers: bool = True,
+        keep_open: bool = False,
+        decompress: bool = False,
+    ) -> tuple[int, dict[str, str], bytes]: ...
+    def stream_to(self, raw_http: bytes, path: str) -> tuple[int, dict[str, str]]: ...
+    def close(self) -> None: ...
+    def abort(self) -> None: ...
+    def start_notarize(self) -> None: ...
+    def finalize_notarize(
+        self,
+        verify_with_pub_key: Optional[bytes] = None,
+        format: str = "bincode",
+        progress: Optional[Progress] = None,
+        hash_alg: str = "sha256",
+        commitments: Optional[list[str]] = None,
+        secrets_passphrase: Optional[str] = None,
+        sent_granularity: Optional[int] = None,
+        recv_granularity: Optional[int] = None,
+        auto_reset: bool = True,
+        max_attestation_bytes: Optional[int] = None,
+        sent_commit_ranges: Optional[list[tuple[int, int]]] = None,
+        recv_commit_ranges: Optional[list[tuple[int, int]]] = None,
+    ) -> tuple[bytes, bytes]: ...
+    def finalize_notarize_async(
+        self,
+        verify_with_pub_key: Optional[bytes] = None,
+        format: str = "bincode",
+        progress: Optional[Progress] = None,
+        hash_alg: str = "sha256",
+        commitments: Optional[list[str]] = None,
+        secrets_passphrase: Optional[str] = None,
+        sent_granularity: Optional[int] = None,
+        recv_granularity: Optional[int] = None,
+        auto_reset: bool = True,
+        max_attestation_bytes: Optional[int] = None,
+        sent_commit_ranges: Optional[list[tuple[int, int]]] = None,
+        recv_commit_ranges: Optional[list[tuple[int, int]]] = None,
+    ) -> Any: ...
+    def write_attestation(
+        self,
+        attestation_path: str,
+        secrets_path: str,
+        verify_with_pub_key: Optional[bytes] = None,
+        format: str = "bincode",
+        progress: Optional[Progress] = None,
+        hash_alg: str = "sha256",
+        commitments: Optional[list[str]] = None,
+        sent_granularity: Optional[int] = None,
+        recv_granularity: Optional[int] = None,
+        auto_reset: bool = True,
+        sent_commit_ranges: Optional[list[tuple[int, int]]] = None,
+        recv_commit_ranges: Optional[list[tuple[int, int]]] = None,
+    ) -> None: ...
+    def cancel(self) -> None: ...
+    def cancel_handle(self) -> PyCancelHandle: ...
+    def notarize_many(
+        self,
+        requests: list[dict[str, Any]],
+        verify_with_pub_key: Optional[bytes] = None,
+        format: str = "bincode",
+    ) -> list[dict[str, Any]]: ...
+    def server_cert_chain(self) -> list[bytes]: ...
+    def tls_info(self) -> dict[str, str]: ...
+    def state(self) -> str: ...
+    def session_id(self) -> str: ...
+    def protocol_version(self) -> str: ...
+    def session_age_seconds(self) -> float: ...
+    def negotiated_limits(self) -> dict[str, int]: ...
+    def committed_transcript(self) -> tuple[bytes, bytes]: ...
+    def sent_len(self) -> int: ...
+    def recv_len(self) -> int: ...
+    @staticmethod
+    def query_notary_info(
+        host: str,
+        port: int,
+        tls: bool = False,
+        root_cert_pem: Optional[bytes] = None,
+        timeout_seconds: int = 10,
+    ) -> dict[str, Any]: ...
+    @staticmethod
+    def save_attestation(attestation_bytes: bytes, path: str) -> None: ...
+    def __enter__(self) -> "PyProver": ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> None: ...
+
+class PySendableProver:
+    """Takes the arguments of `PyProver`."""
+
+    def __init__(self, *args: Any, **kwargs: Any) -> None: ...
+    def new_session(self) -> None: ...
+    def reset(self) -> None: ...
+    def connect(
+        self,
+        server_host: str,
+        server_port: int,
+        prefer_ipv4: bool = False,
+        proxy_url: Optional[str] = None,
+        pin_cert_sha256: Optional[str] = None,
+        tcp_nodelay: bool = False,
+        keepalive_seconds: Optional[int] = None,
+        sni: Optional[str] = None,
+        server_addr: Optional[str] = None,
+    ) -> None: ...
+    def send_request(
+        self, raw_http: bytes, until_complete: bool = False, keep_open: bool = False
+    ) -> bytes: ...
+    def send_http(
+        self,
+        method: str,
+        path: str,
+        headers: Optional[dict[str, str]] = None,
+        body: Optional[bytes] = None,
+        default_headers: bool = True,
+        keep_open: bool = False,
+        decompress: bool = False,
+    ) -> tuple[int, dict[str, str], bytes]: ...
+    def close(self) -> None: ...
+    def stream_to(self, raw_http: bytes, path: str) -> tuple[int, dict[str, str]]: ...
+    def abort(self) -> None: ...
+    def start_notarize(self) -> None: ...
+    def finalize_notarize(
+        self,
+        verify_with_pub_key: Optional[bytes] = None,
+        format: str = "bincode",
+        progress: Optional[Progress] = None,
+        hash_alg: str = "sha256",
+        commitments: Optional[list[str]] = None,
+        secrets_passphrase: Optional[str] = None,
+        sent_granularity: Optional[int] = None,
+        recv_granularity: Optional[int] = None,
+        auto_reset: bool = True,
+        max_attestation_bytes: Optional[int] = None,
+        sent_commit_ranges: Optional[list[tuple[int, int]]] = None,
+        recv_commit_ranges: Optional[list[tuple[int, int]]] = None,
+    ) -> tuple[bytes, bytes]: ...
+    def write_attestation(
+        self,
+        attestation_path: str,
+        secrets_path: str,
+        verify_with_pub_key: Optional[bytes] = None,
+        format: str = "bincode",
+        progress: Optional[Progress] = None,
+        hash_alg: str = "sha256",
+        commitments: Optional[list[str]] = None,
+        sent_granularity: Optional[int] = None,
+        recv_granularity: Optional[int] = None,
+        auto_reset: bool = True,
+        sent_commit_ranges: Optional[list[tuple[int, int]]] = None,
+        recv_commit_ranges: Optional[list[tuple[int, int]]] = None,
+    ) -> None: ...
+    def notarize_many(
+        self,
+        requests: list[dict[str, Any]],
+        verify_with_pub_key: Optional[bytes] = None,
+        format: str = "bincode",
+    ) -> list[dict[str, Any]]: ...
+    def server_cert_chain(self) -> list[bytes]: ...
+    def tls_info(self) -> dict[str, str]: ...
+    def state(self) -> str: ...
+    def session_id(self) -> str: ...
+    def protocol_version(self) -> str: ...
+    def session_age_seconds(self) -> float: ...
+    def negotiated_limits(self) -> dict[str, int]: ...
+    def committed_transcript(self) -> tuple[bytes, bytes]: ...
+    def sent_len(self) -> int: ...
+    def recv_len(self) -> int: ...
+    def cancel(self) -> None: ...
+    def cancel_handle(self) -> PyCancelHandle: ...
+    def __enter__(self) -> "PySendableProver": ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> None: ...
+
+class PyNotary:
+    def __init__(
+        self,
+        host: str,
+        port: int,
+        max_sent_data: int,
+        max_recv_data: int,
+        timeout_seconds: int,
+        tls_enabled: bool,
+        tls_cert_path: Optional[str] = None,
+        tls_key_path: Optional[str] = None,
+        notary_key_path: Optional[str] = None,
+        notary_pub_key_path: Optional[str] = None,
+        *,
+        tls_cert_pem: Optional[bytes] = None,
+        tls_key_pem: Optional[bytes] = None,
+        notary_key_pem: Optional[bytes] = None,
+        notary_pub_key_pem: Optional[bytes] = None,
+        authorization_whitelist_csv: Optional[str] = None,
+        log_level: str = "info",
+        runtime: Optional[PyRuntime] = None,
+        tenant_limits: Optional[dict[str, tuple[int, int]]] = None,
+        unix_socket_path: Optional[str] = None,
+        log_file: Optional[str] = None,
+        log_file_max_bytes: int = 10485760,
+        log_file_backups: int = 3,
+        worker_threads: Optional[int] = None,
+        html_info: Optional[str] = None,
+        max_concurrent_sessions: Optional[int] = None,
+    ) -> None: ...
+    @classmethod
+    def with_runtime(cls, runtime: PyRuntime, *args: Any, **kwargs: Any) -> "PyNotary": ...
+    @staticmethod
+    def dev(
+        host: str = "127.0.0.1",
+        port: int = 0,
+        max_sent_data: int = 10000,
+        max_recv_data: int = 10000,
+        timeout_seconds: int = 60,
+        log_level: str = "info",
+    ) -> "PyNotary": ...
+    def start(self) -> None: ...
+    def set_html_info(self, html_info: str) -> None: ...
+    def active_sessions(self) -> int: ...
+    def is_running(self) -> bool: ...
+    def stop(self, timeout_seconds: Optional[int] = None) -> None: ...
+    def bound_port(self) -> int: ...
+    def reload_whitelist(self) -> int: ...
+    def add_token(self, token: str, name: str = "tlsnpy") -> bool: ...
+    def remove_token(self, token: str) -> bool: ...
+    def signing_algorithm(self) -> str: ...
+    def rotate_signing_key(self, new_private_pem: bytes, new_public_pem: bytes) -> None: ...
+    def config(self) -> dict[str, Any]: ...
+    def public_key_pem(self) -> bytes: ...
+    def tls_cert_pem(self) -> bytes: ...
+    @staticmethod
+    def generate_keypair(curve: str = "secp256k1") -> tuple[bytes, bytes]: ...
+    def __enter__(self) -> "PyNotary": ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> None: ...
+
+class PyPresentationBuilder:
+    def __init__(
+        self, attestation_bytes: bytes, secrets_bytes: bytes, passphrase: Optional[str] = None
+    ) -> None: ...
+    def reveal_sent(self, ranges: list[tuple[int, int]]) -> None: ...
+    def reveal_recv(self, ranges: list[tuple[int, int]]) -> None: ...
+    def reveal_headers_only(self, sent: bool = True, recv: bool = True) -> None: ...
+    def reveal_body_only(self, sent: bool = True, recv: bool = True) -> None: ...
+    def redact_sent_regex(self, pattern: str) -> None: ...
+    def redact_recv_regex(self, pattern: str) -> None: ...
+    def build(self) -> bytes: ...
+
+class PyVerifier:
+    def __init__(self) -> None: ...
+    def verify(
+        self, attestation_bytes: bytes, notary_pub_key_pem: Union[bytes, list[bytes]]
+    ) -> dict[str, Any]: ...
+    @staticmethod
+    def verify_presentation(
+        presentation_bytes: bytes, notary_pub_key_pem: bytes
+    ) -> dict[str, Any]: ...
+
+def notarize_url(
+    notary_host: str,
+    notary_port: int,
+    url: str,
+    method: str = "GET",
+    headers: Optional[dict[str, str]] = None,
+    body: Optional[bytes] = None,
+    *,
+    notary_tls: bool = False,
+    max_sent_data: int = 10000,
+    max_recv_data: int = 10000,
+) -> bytes: ...
+def load_attestation(path: str) -> bytes: ...
+def attestation_to_json(attestation_bytes: bytes) -> str: ...
+def inspect_attestation(attestation_bytes: bytes) -> dict[str, Any]: ...
+def attestation_time(attestation_bytes: bytes) -> int: ...
+def attestation_size(attestation_bytes: bytes, format: str = "bincode") -> int: ...
+def set_bincode_limit(max_bytes: Optional[int] = None) -> None: ...
+def diff_attestations(a: bytes, b: bytes) -> dict[str, tuple[Optional[str], Optional[str]]]: ...
+def encrypt_secrets(secrets_bytes: bytes, passphrase: str) -> bytes: ...
+def decrypt_secrets(encrypted_secrets: bytes, passphrase: str) -> bytes: ...